@@ -92,10 +92,13 @@ fn solve2(data: &str) -> u64 {
   oxygen * co2
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
   let data = include_str!("../input.txt");
-  println!("Part 1: {}", solve1(data));
-  println!("Part 2: {}", solve2(data));
+  aoc::report::part(1, || solve1(data));
+  aoc::report::part(2, || solve2(data));
 }
 
 #[test]
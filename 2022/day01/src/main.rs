@@ -2,40 +2,151 @@
 
 extern crate test;
 
-fn parse(data: &str) -> Vec<Vec<u32>> {
-    data.split("\n\n")
-        .map(|s| {
-            s.split("\n")
-                .filter_map(|n| n.parse::<u32>().ok())
-                .collect()
-        })
-        .collect()
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fmt;
+use std::io::{self, BufRead};
+
+#[derive(Debug)]
+enum CalorieError {
+    Io(io::Error),
+    /// `line` is counted from 1.
+    Malformed {
+        line: usize,
+        text: String,
+    },
+    /// An elf's total no longer fits in a `u64` after `line`.
+    Overflow {
+        line: usize,
+    },
 }
 
-fn sums(groups: Vec<Vec<u32>>) -> Vec<u32> {
-    groups.iter().map(|v| v.iter().sum()).collect()
+impl fmt::Display for CalorieError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CalorieError::Io(e) => write!(f, "failed to read calories: {}", e),
+            CalorieError::Malformed { line, text } => {
+                write!(f, "line {} is not a calorie count: {:?}", line, text)
+            }
+            CalorieError::Overflow { line } => {
+                write!(f, "calorie total overflows at line {}", line)
+            }
+        }
+    }
 }
 
-fn solve1(data: &str) -> u32 {
-    let groups = parse(data);
+impl From<io::Error> for CalorieError {
+    fn from(e: io::Error) -> Self {
+        CalorieError::Io(e)
+    }
+}
 
-    let group_sums = sums(groups);
+/// An elf's calorie total; `index` counts elves from 0 in input order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Elf {
+    index: usize,
+    total: u64,
+}
 
-    *group_sums.iter().max().expect("should not be empty")
+/// Keeps the `k` largest totals seen so far in a min-heap, so memory stays `O(k)`
+/// however many elves there are. Ties go to the elf that came first.
+struct TopElves {
+    k: usize,
+    heap: BinaryHeap<Reverse<(u64, Reverse<usize>)>>,
 }
 
-fn solve2(data: &str) -> u32 {
-    let groups = parse(data);
-    let mut sums = sums(groups);
-    sums.sort();
-    sums.reverse();
-    sums.iter().take(3).sum()
+impl TopElves {
+    fn new(k: usize) -> Self {
+        TopElves {
+            k,
+            heap: BinaryHeap::with_capacity(k + 1),
+        }
+    }
+
+    fn push(&mut self, elf: Elf) {
+        self.heap.push(Reverse((elf.total, Reverse(elf.index))));
+        if self.heap.len() > self.k {
+            self.heap.pop();
+        }
+    }
+
+    /// The kept elves, largest total first.
+    fn into_sorted(self) -> Vec<Elf> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|Reverse((total, Reverse(index)))| Elf { index, total })
+            .collect()
+    }
 }
 
+impl Extend<Elf> for TopElves {
+    fn extend<I: IntoIterator<Item = Elf>>(&mut self, elves: I) {
+        elves.into_iter().for_each(|e| self.push(e));
+    }
+}
+
+/// Reads blank-line separated groups of calorie counts one line at a time and returns
+/// the `k` elves carrying the most, largest first.
+fn top_elves<R: BufRead>(reader: R, k: usize) -> Result<Vec<Elf>, CalorieError> {
+    let mut top = TopElves::new(k);
+    let mut current: Option<Elf> = None;
+    let mut elves = 0;
+    for (i, line) in reader.lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() {
+            top.extend(current.take());
+            continue;
+        }
+        let calories = line.parse::<u64>().map_err(|_| CalorieError::Malformed {
+            line: i + 1,
+            text: line.to_string(),
+        })?;
+        let elf = current.get_or_insert_with(|| {
+            elves += 1;
+            Elf {
+                index: elves - 1,
+                total: 0,
+            }
+        });
+        elf.total = elf
+            .total
+            .checked_add(calories)
+            .ok_or(CalorieError::Overflow { line: i + 1 })?;
+    }
+    top.extend(current);
+    Ok(top.into_sorted())
+}
+
+fn solve1(data: &str) -> u64 {
+    top_elves(data.as_bytes(), 1)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .first()
+        .expect("should not be empty")
+        .total
+}
+
+fn solve2(data: &str) -> u64 {
+    top_elves(data.as_bytes(), 3)
+        .unwrap_or_else(|e| panic!("{}", e))
+        .iter()
+        .map(|e| e.total)
+        .sum()
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = top_elves(data, 3);
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -55,6 +166,57 @@ mod tests {
         assert_eq!(solve2(data), 45000)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 70369)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 203002)
+    }
+
+    #[test]
+    fn test_top_elves() {
+        let data = include_str!("../example.txt");
+        let top = top_elves(data.as_bytes(), 3).unwrap();
+        assert_eq!(
+            top.iter().map(|e| e.index).collect::<Vec<_>>(),
+            vec![3, 2, 4]
+        );
+        assert_eq!(top_elves(data.as_bytes(), 10).unwrap().len(), 5);
+        let ties = top_elves("5\n\n5\n\n\n\n3\n2\n\n5000000000".as_bytes(), 3).unwrap();
+        assert_eq!(
+            ties,
+            vec![
+                Elf {
+                    index: 3,
+                    total: 5_000_000_000
+                },
+                Elf { index: 0, total: 5 },
+                Elf { index: 1, total: 5 }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_malformed_line() {
+        let err = top_elves("100\n2oo\n".as_bytes(), 1).unwrap_err();
+        assert!(matches!(
+            err,
+            CalorieError::Malformed { line: 2, ref text } if text == "2oo"
+        ));
+        let err = top_elves("18446744073709551615\n1\n".as_bytes(), 1).unwrap_err();
+        assert!(matches!(err, CalorieError::Overflow { line: 2 }));
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
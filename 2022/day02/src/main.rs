@@ -1,128 +1,247 @@
 #![feature(test)]
 extern crate test;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum Hand {
-    ROCK = 1,
-    PAPER = 2,
-    SCISSORS = 3,
-}
-
-impl Hand {
-    fn from(s: &str) -> Hand {
-        match s {
-            "A" | "X" => Hand::ROCK,
-            "B" | "Y" => Hand::PAPER,
-            "C" | "Z" => Hand::SCISSORS,
-            _ => panic!("Hand::from(\"{}\")", s),
-        }
-    }
+use std::collections::HashMap;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Outcome {
+    Loss = 0,
+    Draw = 3,
+    Win = 6,
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-enum RoundResult {
-    LOSS = 0,
-    DRAW = 3,
-    WIN = 6,
+const OUTCOMES: [Outcome; 3] = [Outcome::Loss, Outcome::Draw, Outcome::Win];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum GameError {
+    EvenMoveCount(usize),
+    UnknownSymbol(String),
+    MalformedRound(String),
+    /// No one-to-one reading maps this many symbols onto moves or outcomes.
+    NoReading(usize),
 }
 
-type Goal = RoundResult;
+/// A game of cyclic dominance over an odd number of moves. Move `i` scores `i + 1`
+/// and beats the `n / 2` moves before it, wrapping around, so every move beats and
+/// loses to exactly half of the others.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Game {
+    moves: Vec<&'static str>,
+}
+
+const ROCK_PAPER_SCISSORS: [&str; 3] = ["rock", "paper", "scissors"];
+
+#[cfg(test)]
+const ROCK_PAPER_SCISSORS_LIZARD_SPOCK: [&str; 5] =
+    ["rock", "spock", "paper", "lizard", "scissors"];
+
+impl Game {
+    fn new(moves: &[&'static str]) -> Result<Game, GameError> {
+        if moves.len().is_multiple_of(2) {
+            return Err(GameError::EvenMoveCount(moves.len()));
+        }
+        Ok(Game {
+            moves: moves.to_vec(),
+        })
+    }
 
-impl Goal {
-    fn from(s: &str) -> Goal {
-        match s {
-            "X" => Goal::LOSS,
-            "Y" => Goal::DRAW,
-            "Z" => Goal::WIN,
-            _ => panic!("Hand::from(\"{}\")", s),
+    fn outcome(&self, own: usize, opponent: usize) -> Outcome {
+        let n = self.moves.len();
+        match (own + n - opponent) % n {
+            0 => Outcome::Draw,
+            d if d <= n / 2 => Outcome::Win,
+            _ => Outcome::Loss,
         }
     }
+
+    fn score(&self, own: usize, opponent: usize) -> u64 {
+        self.outcome(own, opponent) as u64 + own as u64 + 1
+    }
+
+    /// The highest scoring move that ends the round with `outcome`.
+    fn move_for(&self, opponent: usize, outcome: Outcome) -> usize {
+        (0..self.moves.len())
+            .rev()
+            .find(|own| self.outcome(*own, opponent) == outcome)
+            .unwrap()
+    }
+}
+
+/// How to read the second column of the strategy guide.
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum OwnColumn {
+    Move(HashMap<String, usize>),
+    Outcome(HashMap<String, Outcome>),
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-struct RoundP1 {
-    own: Hand,
-    opponent: Hand,
+/// Symbol mappings for both columns; moves are indices into the game's move list.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Strategy {
+    opponent: HashMap<String, usize>,
+    own: OwnColumn,
 }
 
-impl RoundP1 {
-    fn play(self) -> RoundResult {
-        match (self.own, self.opponent) {
-            (Hand::ROCK, Hand::SCISSORS)
-            | (Hand::PAPER, Hand::ROCK)
-            | (Hand::SCISSORS, Hand::PAPER) => RoundResult::WIN,
-            (Hand::SCISSORS, Hand::ROCK)
-            | (Hand::ROCK, Hand::PAPER)
-            | (Hand::PAPER, Hand::SCISSORS) => RoundResult::LOSS,
-            _ => RoundResult::DRAW,
+fn symbols<T: Copy>(symbols: &[&str], values: &[T]) -> HashMap<String, T> {
+    symbols
+        .iter()
+        .map(|s| s.to_string())
+        .zip(values.iter().copied())
+        .collect()
+}
+
+impl OwnColumn {
+    /// The reading as `symbol = meaning` pairs in symbol order.
+    fn describe(&self, game: &Game) -> String {
+        let mut pairs = match self {
+            OwnColumn::Move(moves) => moves
+                .iter()
+                .map(|(s, m)| format!("{} = {}", s, game.moves[*m]))
+                .collect::<Vec<_>>(),
+            OwnColumn::Outcome(outcomes) => outcomes
+                .iter()
+                .map(|(s, o)| format!("{} = {:?}", s, o))
+                .collect(),
+        };
+        pairs.sort();
+        pairs.join(", ")
+    }
+}
+
+impl Strategy {
+    fn opponent_move(&self, symbol: &str) -> Result<usize, GameError> {
+        self.opponent
+            .get(symbol)
+            .copied()
+            .ok_or_else(|| GameError::UnknownSymbol(symbol.to_string()))
+    }
+
+    fn own_move(&self, game: &Game, opponent: usize, symbol: &str) -> Result<usize, GameError> {
+        let unknown = || GameError::UnknownSymbol(symbol.to_string());
+        match &self.own {
+            OwnColumn::Move(moves) => moves.get(symbol).copied().ok_or_else(unknown),
+            OwnColumn::Outcome(outcomes) => outcomes
+                .get(symbol)
+                .map(|o| game.move_for(opponent, *o))
+                .ok_or_else(unknown),
         }
     }
-    fn score(self) -> u64 {
-        self.play() as u64 + self.own as u64
+
+    fn total_score(&self, game: &Game, rounds: &[(&str, &str)]) -> Result<u64, GameError> {
+        rounds
+            .iter()
+            .map(|(opponent, own)| {
+                let opponent = self.opponent_move(opponent)?;
+                Ok(game.score(self.own_move(game, opponent, own)?, opponent))
+            })
+            .sum()
     }
 }
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-struct RoundWithGoal {
-    opponent: Hand,
-    goal: Goal,
+fn permutations(n: usize) -> Vec<Vec<usize>> {
+    if n == 0 {
+        return vec![vec![]];
+    }
+    permutations(n - 1)
+        .into_iter()
+        .flat_map(|p| {
+            (0..n).map(move |i| {
+                let mut q = p.clone();
+                q.insert(i, n - 1);
+                q
+            })
+        })
+        .collect()
 }
 
-impl RoundWithGoal {
-    fn get_own_hand_for_result(self, r: Goal) -> Hand {
-        match r {
-            Goal::WIN => match self.opponent {
-                Hand::ROCK => Hand::PAPER,
-                Hand::PAPER => Hand::SCISSORS,
-                Hand::SCISSORS => Hand::ROCK,
-            },
-            Goal::LOSS => match self.opponent {
-                Hand::ROCK => Hand::SCISSORS,
-                Hand::PAPER => Hand::ROCK,
-                Hand::SCISSORS => Hand::PAPER,
-            },
-            _ => self.opponent,
+/// Tries every one-to-one reading of `own_symbols` as moves and, when there are
+/// three symbols, as outcomes, and returns the reading with the highest total.
+fn best_strategy(
+    game: &Game,
+    opponent: &HashMap<String, usize>,
+    own_symbols: &[&str],
+    rounds: &[(&str, &str)],
+) -> Result<(Strategy, u64), GameError> {
+    let mut readings = vec![];
+    if own_symbols.len() <= game.moves.len() {
+        for p in permutations(game.moves.len()) {
+            readings.push(OwnColumn::Move(symbols(own_symbols, &p)));
+        }
+    }
+    if own_symbols.len() == OUTCOMES.len() {
+        for p in permutations(OUTCOMES.len()) {
+            let outcomes = p.iter().map(|i| OUTCOMES[*i]).collect::<Vec<_>>();
+            readings.push(OwnColumn::Outcome(symbols(own_symbols, &outcomes)));
+        }
+    }
+    let mut best: Option<(Strategy, u64)> = None;
+    for own in readings {
+        let strategy = Strategy {
+            opponent: opponent.clone(),
+            own,
+        };
+        let score = strategy.total_score(game, rounds)?;
+        if best.as_ref().is_none_or(|(_, s)| score > *s) {
+            best = Some((strategy, score));
         }
     }
+    best.ok_or(GameError::NoReading(own_symbols.len()))
 }
 
-fn parse(data: &str) -> Vec<(&str, &str)> {
-    data.split("\n").filter_map(|l| l.split_once(" ")).collect()
+fn parse(data: &str) -> Result<Vec<(&str, &str)>, GameError> {
+    data.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            l.split_once(' ')
+                .ok_or_else(|| GameError::MalformedRound(l.to_string()))
+        })
+        .collect()
 }
 
-fn solve1(data: &Vec<(&str, &str)>) -> u64 {
-    let rounds = data.iter().map(|l| {
-        let (opponent, own) = l;
-        RoundP1 {
-            opponent: Hand::from(opponent),
-            own: Hand::from(own),
-        }
-    });
-    rounds.map(|r| r.score()).sum()
+fn elf_opponent() -> HashMap<String, usize> {
+    symbols(&["A", "B", "C"], &[0, 1, 2])
 }
 
-fn solve2(data: &Vec<(&str, &str)>) -> u64 {
-    let round_goals = data.iter().map(|l| {
-        let (opponent, goal) = l;
-        RoundWithGoal {
-            opponent: Hand::from(opponent),
-            goal: Goal::from(goal),
-        }
-    });
+fn solve1(data: &[(&str, &str)]) -> u64 {
+    let game = Game::new(&ROCK_PAPER_SCISSORS).unwrap();
+    let strategy = Strategy {
+        opponent: elf_opponent(),
+        own: OwnColumn::Move(symbols(&["X", "Y", "Z"], &[0, 1, 2])),
+    };
+    strategy.total_score(&game, data).unwrap()
+}
 
-    let rounds = round_goals.map(|rg| RoundP1 {
-        opponent: rg.opponent,
-        own: rg.get_own_hand_for_result(rg.goal),
-    });
+fn solve2(data: &[(&str, &str)]) -> u64 {
+    let game = Game::new(&ROCK_PAPER_SCISSORS).unwrap();
+    let strategy = Strategy {
+        opponent: elf_opponent(),
+        own: OwnColumn::Outcome(symbols(&["X", "Y", "Z"], &OUTCOMES)),
+    };
+    strategy.total_score(&game, data).unwrap()
+}
 
-    rounds.map(|r| r.score()).sum()
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = parse(&String::from_utf8_lossy(data));
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    let parsed = parse(data);
-    println!("Part 1: {}", solve1(&parsed));
-    println!("Part 2: {}", solve2(&parsed));
+    let parsed = parse(data).unwrap();
+    aoc::report::part(1, || solve1(&parsed));
+    aoc::report::part(2, || solve2(&parsed));
+    if std::env::args().any(|a| a == "--best-strategy") {
+        let game = Game::new(&ROCK_PAPER_SCISSORS).unwrap();
+        let (strategy, score) =
+            best_strategy(&game, &elf_opponent(), &["X", "Y", "Z"], &parsed).unwrap();
+        println!(
+            "Best strategy: {} scores {}",
+            strategy.own.describe(&game),
+            score
+        );
+    }
 }
 
 #[cfg(test)]
@@ -133,24 +252,105 @@ mod tests {
     #[test]
     fn test_part1() {
         let data = include_str!("../example.txt");
-        assert_eq!(solve1(&parse(&data)), 15)
+        assert_eq!(solve1(&parse(data).unwrap()), 15)
     }
 
     #[test]
     fn test_part2() {
         let data = include_str!("../example.txt");
-        assert_eq!(solve2(&parse(&data)), 12)
+        assert_eq!(solve2(&parse(data).unwrap()), 12)
+    }
+
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(&parse(data).unwrap()), 8392)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(&parse(data).unwrap()), 10116)
+    }
+
+    #[test]
+    fn test_rock_paper_scissors_lizard_spock() {
+        let game = Game::new(&ROCK_PAPER_SCISSORS_LIZARD_SPOCK).unwrap();
+        let index = |name| game.moves.iter().position(|m| *m == name).unwrap();
+        let beats = [
+            ("scissors", "paper"),
+            ("paper", "rock"),
+            ("rock", "lizard"),
+            ("lizard", "spock"),
+            ("spock", "scissors"),
+            ("scissors", "lizard"),
+            ("lizard", "paper"),
+            ("paper", "spock"),
+            ("spock", "rock"),
+            ("rock", "scissors"),
+        ];
+        for (winner, loser) in beats {
+            assert_eq!(game.outcome(index(winner), index(loser)), Outcome::Win);
+            assert_eq!(game.outcome(index(loser), index(winner)), Outcome::Loss);
+        }
+        assert_eq!(game.move_for(index("rock"), Outcome::Win), index("paper"));
+        assert_eq!(
+            game.move_for(index("rock"), Outcome::Loss),
+            index("scissors")
+        );
+        assert_eq!(
+            Game::new(&["a", "b", "c", "d"]),
+            Err(GameError::EvenMoveCount(4))
+        );
+    }
+
+    #[test]
+    fn test_best_strategy() {
+        let game = Game::new(&ROCK_PAPER_SCISSORS).unwrap();
+        let rounds = parse(include_str!("../example.txt")).unwrap();
+        let (strategy, score) =
+            best_strategy(&game, &elf_opponent(), &["X", "Y", "Z"], &rounds).unwrap();
+        assert_eq!(score, 24);
+        assert_eq!(strategy.total_score(&game, &rounds), Ok(24));
+        assert_eq!(
+            strategy.own,
+            OwnColumn::Move(symbols(&["X", "Y", "Z"], &[2, 1, 0]))
+        );
+        assert_eq!(
+            strategy.own.describe(&game),
+            "X = scissors, Y = paper, Z = rock"
+        );
+        assert_eq!(
+            best_strategy(&game, &elf_opponent(), &["W", "X", "Y", "Z"], &rounds),
+            Err(GameError::NoReading(4))
+        );
+        let spock = Game::new(&ROCK_PAPER_SCISSORS_LIZARD_SPOCK).unwrap();
+        assert!(best_strategy(&spock, &elf_opponent(), &["V", "W", "X", "Y", "Z"], &[]).is_ok());
+        assert_eq!(
+            best_strategy(
+                &spock,
+                &elf_opponent(),
+                &["1", "2", "3", "4", "5", "6"],
+                &[]
+            ),
+            Err(GameError::NoReading(6))
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
     }
 
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
-        b.iter(|| solve1(&parse(&data)));
+        b.iter(|| solve1(&parse(data).unwrap()));
     }
 
     #[bench]
     fn bench_part2(b: &mut Bencher) {
         let data = include_str!("../input.txt");
-        b.iter(|| solve2(&parse(&data)));
+        b.iter(|| solve2(&parse(data).unwrap()));
     }
 }
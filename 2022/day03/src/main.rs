@@ -1,86 +1,188 @@
 #![feature(test)]
 extern crate test;
 
-use std::collections::HashSet;
+use std::fmt;
 
-#[derive(Debug, Clone)]
-struct Rucksack {
-    first_compartment: String,
-    second_compartment: String,
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RucksackError {
+    OddLength(String),
+    InvalidItem(char),
+    EmptyGroup,
+    IncompleteGroup {
+        size: usize,
+        found: usize,
+    },
+    /// `line` is the first line of the rucksack or group, counted from 0.
+    NoCommonItem {
+        line: usize,
+    },
+    MultipleCommonItems {
+        line: usize,
+        priorities: Vec<u64>,
+    },
+}
+
+impl fmt::Display for RucksackError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RucksackError::OddLength(s) => write!(f, "{} has an odd number of items", s),
+            RucksackError::InvalidItem(c) => write!(f, "{:?} is not an item", c),
+            RucksackError::EmptyGroup => write!(f, "groups need at least one rucksack"),
+            RucksackError::IncompleteGroup { size, found } => {
+                write!(f, "last group has {} of {} rucksacks", found, size)
+            }
+            RucksackError::NoCommonItem { line } => {
+                write!(f, "no common item at line {}", line)
+            }
+            RucksackError::MultipleCommonItems { line, priorities } => {
+                write!(f, "items {:?} are all common at line {}", priorities, line)
+            }
+        }
+    }
+}
+
+fn char_to_prio(c: char) -> Result<u64, RucksackError> {
+    match c {
+        'a'..='z' => Ok((c as u64) - ('a' as u64) + 1),
+        'A'..='Z' => Ok((c as u64) - ('A' as u64) + 27),
+        _ => Err(RucksackError::InvalidItem(c)),
+    }
 }
 
-fn char_to_prio(c: char) -> u64 {
-    match c.is_ascii_lowercase() {
-        true => (c as u64) - ('a' as u64) + 1,
-        false => (c as u64) - ('A' as u64) + 27,
+/// A set of items as a bitmask with bit `p` set for an item of priority `p`, 1..=52.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ItemSet(u64);
+
+impl ItemSet {
+    const ALL: ItemSet = ItemSet(((1 << 52) - 1) << 1);
+
+    fn from(items: &str) -> Result<ItemSet, RucksackError> {
+        items
+            .chars()
+            .try_fold(0u64, |mask, c| Ok(mask | 1 << char_to_prio(c)?))
+            .map(ItemSet)
     }
+
+    fn intersection(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 & other.0)
+    }
+
+    fn union(self, other: ItemSet) -> ItemSet {
+        ItemSet(self.0 | other.0)
+    }
+
+    fn priorities(self) -> Vec<u64> {
+        (1..=52).filter(|p| self.0 & 1 << p != 0).collect()
+    }
+
+    /// Priority of the only item in the set, or an error naming `line` otherwise.
+    fn single(self, line: usize) -> Result<u64, RucksackError> {
+        match self.0.count_ones() {
+            0 => Err(RucksackError::NoCommonItem { line }),
+            1 => Ok(self.0.trailing_zeros() as u64),
+            _ => Err(RucksackError::MultipleCommonItems {
+                line,
+                priorities: self.priorities(),
+            }),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Rucksack {
+    first_compartment: ItemSet,
+    second_compartment: ItemSet,
 }
 
 impl Rucksack {
-    fn from(s: &str) -> Rucksack {
-        assert!(
-            s.len() % 2 == 0,
-            "{} len {} was not divisible by 2",
-            s,
-            s.len()
-        );
-        let (first, second) = s.split_at(s.len() / 2);
-        Rucksack {
-            first_compartment: first.chars().collect(),
-            second_compartment: second.chars().collect(),
+    fn from(s: &str) -> Result<Rucksack, RucksackError> {
+        if let Some(c) = s.chars().find(|c| !c.is_ascii_alphabetic()) {
+            return Err(RucksackError::InvalidItem(c));
+        }
+        if !s.len().is_multiple_of(2) {
+            return Err(RucksackError::OddLength(s.to_string()));
         }
+        let (first, second) = s.split_at(s.len() / 2);
+        Ok(Rucksack {
+            first_compartment: ItemSet::from(first)?,
+            second_compartment: ItemSet::from(second)?,
+        })
     }
 
-    fn find_common_item(self) -> Option<char> {
-        let s2_charset: HashSet<char> = HashSet::from_iter(self.second_compartment.chars().clone());
-        self.first_compartment
-            .chars()
-            .find(|c| s2_charset.contains(&c))
+    fn items(&self) -> ItemSet {
+        self.first_compartment.union(self.second_compartment)
     }
 
-    fn combine_compartments(self) -> String {
-        self.first_compartment + &self.second_compartment
+    fn common_item(&self) -> ItemSet {
+        self.first_compartment.intersection(self.second_compartment)
     }
+}
 
-    fn find_common_item_from_three(self, r2: Rucksack, r3: Rucksack) -> Option<char> {
-        let r1_chars: HashSet<char> = HashSet::from_iter(self.combine_compartments().chars());
-        let r2_chars: HashSet<char> = HashSet::from_iter(r2.combine_compartments().chars());
-        let r3_chars: HashSet<char> = HashSet::from_iter(r3.combine_compartments().chars());
-        (&(&r1_chars & &r2_chars) & &r3_chars)
-            .iter()
-            .next()
-            .copied()
+fn parse(data: &str) -> Result<Vec<Rucksack>, RucksackError> {
+    data.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(Rucksack::from)
+        .collect()
+}
+
+/// Priority of the one item every rucksack of each consecutive group of `size` holds.
+fn group_badges(rucksacks: &[Rucksack], size: usize) -> Result<Vec<u64>, RucksackError> {
+    if size == 0 {
+        return Err(RucksackError::EmptyGroup);
+    }
+    if !rucksacks.len().is_multiple_of(size) {
+        return Err(RucksackError::IncompleteGroup {
+            size,
+            found: rucksacks.len() % size,
+        });
     }
+    rucksacks
+        .chunks(size)
+        .enumerate()
+        .map(|(i, group)| {
+            group
+                .iter()
+                .fold(ItemSet::ALL, |common, r| common.intersection(r.items()))
+                .single(i * size)
+        })
+        .collect()
 }
 
 fn solve1(data: &str) -> u64 {
-    let rucksacks = data.lines().map(Rucksack::from);
-    let commons = rucksacks.filter_map(|r| r.find_common_item());
-    let prios = commons.map(char_to_prio);
-    prios.sum()
+    parse(data)
+        .and_then(|rucksacks| {
+            rucksacks
+                .iter()
+                .enumerate()
+                .map(|(i, r)| r.common_item().single(i))
+                .sum()
+        })
+        .unwrap_or_else(|e| panic!("{}", e))
 }
 
 fn solve2(data: &str) -> u64 {
-    let lines: Vec<&str> = data.split("\n").collect();
-    assert!(lines.len() % 3 == 0);
-    let groups: Vec<Vec<Rucksack>> = lines
-        .chunks(3)
-        .map(|c| c.iter().map(|s| Rucksack::from(s)).collect())
-        .collect();
-    let badges = groups.iter().filter_map(|g| {
-        g[0].clone()
-            .find_common_item_from_three(g[1].clone(), g[2].clone())
-    });
-    let prios = badges.map(char_to_prio);
-    prios.sum()
+    parse(data)
+        .and_then(|rucksacks| group_badges(&rucksacks, 3))
+        .unwrap_or_else(|e| panic!("{}", e))
+        .iter()
+        .sum()
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = parse(&String::from_utf8_lossy(data));
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -109,15 +211,48 @@ mod tests {
         assert_eq!(solve2(data), 2683)
     }
 
+    #[test]
+    fn test_group_sizes() {
+        let rucksacks = parse(include_str!("../example.txt")).unwrap();
+        assert_eq!(
+            group_badges(&rucksacks, 6),
+            Err(RucksackError::NoCommonItem { line: 0 })
+        );
+        assert_eq!(group_badges(&parse("abcd\naxyz").unwrap(), 2), Ok(vec![1]));
+        assert_eq!(
+            group_badges(&rucksacks, 4),
+            Err(RucksackError::IncompleteGroup { size: 4, found: 2 })
+        );
+        assert_eq!(group_badges(&rucksacks, 0), Err(RucksackError::EmptyGroup));
+        assert_eq!(group_badges(&[], 0), Err(RucksackError::EmptyGroup));
+        let twins = parse("abAB\nbaBA").unwrap();
+        assert_eq!(
+            group_badges(&twins, 2),
+            Err(RucksackError::MultipleCommonItems {
+                line: 0,
+                priorities: vec![1, 2, 27, 28]
+            })
+        );
+        assert_eq!(
+            Rucksack::from("a1").err(),
+            Some(RucksackError::InvalidItem('1'))
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
-        b.iter(|| solve1(&data));
+        b.iter(|| solve1(data));
     }
 
     #[bench]
     fn bench_part2(b: &mut Bencher) {
         let data = include_str!("../input.txt");
-        b.iter(|| solve2(&data));
+        b.iter(|| solve2(data));
     }
 }
@@ -1,19 +1,39 @@
 #![feature(test)]
 extern crate test;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SectionError {
+    MalformedSection(String),
+    MalformedPair(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Section {
     start: u32,
     end: u32,
 }
 
 impl Section {
-    fn from(s: &str) -> Section {
-        let (start, end) = s.split_once("-").unwrap();
-        Section {
-            start: start.parse::<u32>().unwrap(),
-            end: end.parse::<u32>().unwrap(),
+    fn from(s: &str) -> Result<Section, SectionError> {
+        let malformed = || SectionError::MalformedSection(s.to_string());
+        let (start, end) = s.split_once("-").ok_or_else(malformed)?;
+        let start = start.parse::<u32>().map_err(|_| malformed())?;
+        let end = end.parse::<u32>().map_err(|_| malformed())?;
+        if start > end {
+            return Err(malformed());
         }
+        Ok(Section { start, end })
+    }
+
+    /// A `u64`, since `0-4294967295` holds one more section than `u32::MAX`.
+    fn len(self) -> u64 {
+        (self.end - self.start) as u64 + 1
+    }
+
+    fn intersection(self, other: Section) -> Option<Section> {
+        let start = self.start.max(other.start);
+        let end = self.end.min(other.end);
+        (start <= end).then_some(Section { start, end })
     }
 }
 
@@ -24,12 +44,14 @@ struct SectionPair {
 }
 
 impl SectionPair {
-    fn from(s: &str) -> SectionPair {
-        let (first, second) = s.split_once(",").unwrap();
-        SectionPair {
-            first: Section::from(first),
-            second: Section::from(second),
-        }
+    fn from(s: &str) -> Result<SectionPair, SectionError> {
+        let (first, second) = s
+            .split_once(",")
+            .ok_or_else(|| SectionError::MalformedPair(s.to_string()))?;
+        Ok(SectionPair {
+            first: Section::from(first)?,
+            second: Section::from(second)?,
+        })
     }
 
     fn one_contains_other(self) -> bool {
@@ -40,28 +62,279 @@ impl SectionPair {
     fn overlaps(self) -> bool {
         !(self.first.end < self.second.start || self.first.start > self.second.end)
     }
+
+    fn overlap_len(self) -> u64 {
+        self.first.intersection(self.second).map_or(0, Section::len)
+    }
+}
+
+/// A static interval tree: the sections sorted by start form an implicit balanced
+/// binary search tree, the node for `lo..hi` sitting at its midpoint and storing the
+/// largest end in that range, so queries skip subtrees that end too early.
+struct IntervalTree {
+    sections: Vec<(Section, usize)>,
+    max_end: Vec<u32>,
+}
+
+impl IntervalTree {
+    /// `sections[i]` is identified by `i` in query results.
+    fn new(sections: &[Section]) -> Self {
+        let mut sorted = sections.iter().copied().zip(0..).collect::<Vec<_>>();
+        sorted.sort_by_key(|(s, _)| (s.start, s.end));
+        let mut tree = IntervalTree {
+            max_end: vec![0; sorted.len()],
+            sections: sorted,
+        };
+        tree.build(0, tree.sections.len());
+        tree
+    }
+
+    fn build(&mut self, lo: usize, hi: usize) -> u32 {
+        if lo >= hi {
+            return 0;
+        }
+        let mid = (lo + hi) / 2;
+        let max_end = self.sections[mid]
+            .0
+            .end
+            .max(self.build(lo, mid))
+            .max(self.build(mid + 1, hi));
+        self.max_end[mid] = max_end;
+        max_end
+    }
+
+    /// Ids of every section sharing at least one section number with `query`.
+    fn overlapping(&self, query: Section) -> Vec<usize> {
+        let mut found = vec![];
+        self.collect(0, self.sections.len(), query, &mut found);
+        found
+    }
+
+    fn collect(&self, lo: usize, hi: usize, query: Section, found: &mut Vec<usize>) {
+        if lo >= hi {
+            return;
+        }
+        let mid = (lo + hi) / 2;
+        if self.max_end[mid] < query.start {
+            return;
+        }
+        self.collect(lo, mid, query, found);
+        let (section, id) = self.sections[mid];
+        if section.start > query.end {
+            return;
+        }
+        if section.end >= query.start {
+            found.push(id);
+        }
+        self.collect(mid + 1, hi, query, found);
+    }
+
+    /// How many sections contain section number `n`.
+    #[cfg(test)]
+    fn coverage_at(&self, n: u32) -> usize {
+        self.overlapping(Section { start: n, end: n }).len()
+    }
+}
+
+/// Every elf's assignment, elf `2 * i` and `2 * i + 1` being the two halves of pair
+/// `i` in the input.
+struct Assignments {
+    elves: Vec<Section>,
+    tree: IntervalTree,
+}
+
+impl Assignments {
+    fn new(pairs: &[SectionPair]) -> Self {
+        let elves = pairs
+            .iter()
+            .flat_map(|p| [p.first, p.second])
+            .collect::<Vec<_>>();
+        Assignments {
+            tree: IntervalTree::new(&elves),
+            elves,
+        }
+    }
+
+    /// Every other elf whose assignment overlaps `elf`'s, with the overlap length.
+    fn overlaps_of(&self, elf: usize) -> Vec<(usize, u64)> {
+        let section = self.elves[elf];
+        let mut overlaps = self
+            .tree
+            .overlapping(section)
+            .into_iter()
+            .filter(|other| *other != elf)
+            .map(|other| {
+                let len = section
+                    .intersection(self.elves[other])
+                    .map_or(0, Section::len);
+                (other, len)
+            })
+            .collect::<Vec<_>>();
+        overlaps.sort();
+        overlaps
+    }
+
+    /// Maximal runs of section numbers between the lowest and highest assigned one,
+    /// each with the number of elves covering it.
+    fn coverage(&self) -> Vec<(Section, usize)> {
+        let mut events = self
+            .elves
+            .iter()
+            .flat_map(|s| [(s.start as u64, 1i64), (s.end as u64 + 1, -1)])
+            .collect::<Vec<_>>();
+        events.sort();
+        let mut runs: Vec<(Section, usize)> = vec![];
+        let mut count = 0i64;
+        for pair in events.windows(2) {
+            let ((at, delta), (next, _)) = (pair[0], pair[1]);
+            count += delta;
+            if at == next {
+                continue;
+            }
+            // Only the final event can sit past `u32::MAX`, so both ends fit.
+            let run = Section {
+                start: at as u32,
+                end: (next - 1) as u32,
+            };
+            match runs.last_mut() {
+                Some((last, c)) if *c == count as usize => last.end = run.end,
+                _ => runs.push((run, count as usize)),
+            }
+        }
+        runs
+    }
+
+    /// Coverage runs whose elf count satisfies `keep`, with adjacent runs merged.
+    fn sections_where(&self, keep: impl Fn(usize) -> bool) -> Vec<Section> {
+        let mut merged: Vec<Section> = vec![];
+        for (run, _) in self.coverage().into_iter().filter(|(_, c)| keep(*c)) {
+            match merged.last_mut() {
+                Some(last) if last.end as u64 + 1 == run.start as u64 => last.end = run.end,
+                _ => merged.push(run),
+            }
+        }
+        merged
+    }
+
+    fn uncovered(&self) -> Vec<Section> {
+        self.sections_where(|c| c == 0)
+    }
+
+    fn covered_by_several(&self) -> Vec<Section> {
+        self.sections_where(|c| c > 1)
+    }
+
+    /// The fewest elves whose assignments still cover every section anyone covers,
+    /// picked greedily: from the first uncovered section, take the elf starting at
+    /// or before it that reaches furthest.
+    fn minimum_cover(&self) -> Vec<usize> {
+        let mut order = (0..self.elves.len()).collect::<Vec<_>>();
+        order.sort_by_key(|e| self.elves[*e].start);
+        let mut chosen = vec![];
+        let mut i = 0;
+        while i < order.len() {
+            let mut covered_to = self.elves[order[i]].start as i64 - 1;
+            loop {
+                let mut best: Option<usize> = None;
+                while i < order.len() && self.elves[order[i]].start as i64 <= covered_to + 1 {
+                    let e = order[i];
+                    if best.is_none_or(|b| self.elves[e].end > self.elves[b].end) {
+                        best = Some(e);
+                    }
+                    i += 1;
+                }
+                match best {
+                    Some(e) if self.elves[e].end as i64 > covered_to => {
+                        chosen.push(e);
+                        covered_to = self.elves[e].end as i64;
+                    }
+                    _ => break,
+                }
+            }
+        }
+        chosen.sort();
+        chosen
+    }
 }
 
 fn solve1(data: &str) -> usize {
-    data.lines()
-        .map(SectionPair::from)
+    parse(data)
+        .unwrap()
+        .iter()
         .filter(|sp| sp.one_contains_other())
         .count()
 }
 
 fn solve2(data: &str) -> usize {
-    data.lines()
-        .map(SectionPair::from)
+    parse(data)
+        .unwrap()
+        .iter()
         .filter(|sp| sp.overlaps())
         .count()
 }
 
+fn parse(data: &str) -> Result<Vec<SectionPair>, SectionError> {
+    data.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(SectionPair::from)
+        .collect()
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let Ok(pairs) = parse(&String::from_utf8_lossy(data)) else {
+        return;
+    };
+    pairs.iter().for_each(|p| {
+        p.overlap_len();
+    });
+    let assignments = Assignments::new(&pairs);
+    (0..assignments.elves.len()).for_each(|e| {
+        assignments.overlaps_of(e);
+    });
+    assignments.uncovered();
+    assignments.covered_by_several();
+    assignments.minimum_cover();
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
+    let pairs = parse(data).unwrap();
+    let assignments = Assignments::new(&pairs);
+    let overlap: u64 = pairs.iter().map(|p| p.overlap_len()).sum();
+    println!("Overlapping sections in pairs: {}", overlap);
+    if let Some((elf, overlaps)) = (0..assignments.elves.len())
+        .map(|e| (e, assignments.overlaps_of(e)))
+        .max_by_key(|(_, o)| o.len())
+    {
+        println!(
+            "Elf {} overlaps the most others: {} elves, {} sections",
+            elf,
+            overlaps.len(),
+            overlaps.iter().map(|(_, len)| len).sum::<u64>()
+        );
+    }
+    println!("Uncovered sections: {:?}", assignments.uncovered());
+    println!(
+        "Sections covered by several elves: {}",
+        assignments
+            .covered_by_several()
+            .iter()
+            .map(|s| s.len())
+            .sum::<u64>()
+    );
+    println!(
+        "Elves needed to cover every section: {}",
+        assignments.minimum_cover().len()
+    );
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -78,6 +351,88 @@ mod tests {
         assert_eq!(solve2(data), 4)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 644)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 926)
+    }
+
+    #[test]
+    fn test_interval_tree_matches_brute_force() {
+        let pairs = parse(include_str!("../input.txt")).unwrap();
+        let assignments = Assignments::new(&pairs);
+        for elf in (0..assignments.elves.len()).step_by(97) {
+            let section = assignments.elves[elf];
+            let expected = (0..assignments.elves.len())
+                .filter(|o| *o != elf)
+                .filter_map(|o| {
+                    section
+                        .intersection(assignments.elves[o])
+                        .map(|i| (o, i.len()))
+                })
+                .collect::<Vec<_>>();
+            assert_eq!(assignments.overlaps_of(elf), expected);
+        }
+        assert_eq!(assignments.tree.coverage_at(0), 0);
+    }
+
+    #[test]
+    fn test_coverage() {
+        let pairs = parse(include_str!("../example.txt")).unwrap();
+        assert_eq!(pairs.iter().map(|p| p.overlap_len()).sum::<u64>(), 10);
+        let assignments = Assignments::new(&pairs);
+        assert_eq!(assignments.tree.coverage_at(5), 7);
+        assert_eq!(assignments.uncovered(), vec![]);
+        assert_eq!(
+            assignments.covered_by_several(),
+            vec![Section { start: 2, end: 8 }]
+        );
+        assert_eq!(assignments.minimum_cover(), vec![5, 6]);
+
+        let gaps = Assignments::new(&parse("1-2,5-6\n2-3,9-9").unwrap());
+        assert_eq!(
+            gaps.uncovered(),
+            vec![Section { start: 4, end: 4 }, Section { start: 7, end: 8 }]
+        );
+        assert_eq!(
+            gaps.covered_by_several(),
+            vec![Section { start: 2, end: 2 }]
+        );
+        assert_eq!(gaps.minimum_cover(), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn test_sections_up_to_u32_max() {
+        let pairs = parse("0-4294967295,1-2\n4294967295-4294967295,7-9").unwrap();
+        assert_eq!(pairs[0].first.len(), 1 << 32);
+        assert_eq!(pairs[0].overlap_len(), 2);
+        let assignments = Assignments::new(&pairs);
+        assert_eq!(assignments.overlaps_of(2), vec![(0, 1)]);
+        assert_eq!(assignments.uncovered(), vec![]);
+        assert_eq!(
+            assignments.covered_by_several(),
+            vec![
+                Section { start: 1, end: 2 },
+                Section { start: 7, end: 9 },
+                Section {
+                    start: u32::MAX,
+                    end: u32::MAX
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
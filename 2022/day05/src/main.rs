@@ -1,114 +1,261 @@
 #![feature(test)]
 extern crate test;
 
-use regex::Regex;
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CrateError {
+    MissingMoves,
+    MissingLabels,
+    MalformedMove(String),
+    UnknownStack(String),
+    NotEnoughCrates {
+        label: String,
+        has: usize,
+        wanted: usize,
+    },
+}
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 struct Move {
-    count: u8,
-    from: usize,
-    to: usize,
+    count: usize,
+    from: String,
+    to: String,
 }
 
 impl Move {
-    fn from(s: &str) -> Move {
-        let move_regex: Regex = Regex::new(r"^move (\d+) from (\d+) to (\d+)$").unwrap();
-        for cap in move_regex.captures_iter(s) {
-            return Move {
-                count: cap[1].parse::<u8>().unwrap(),
-                from: cap[2].parse::<usize>().unwrap() - 1,
-                to: cap[3].parse::<usize>().unwrap() - 1,
-            };
+    fn from(s: &str) -> Result<Move, CrateError> {
+        let malformed = || CrateError::MalformedMove(s.to_string());
+        match s.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            ["move", count, "from", from, "to", to] => Ok(Move {
+                count: count.parse::<usize>().map_err(|_| malformed())?,
+                from: from.to_string(),
+                to: to.to_string(),
+            }),
+            _ => Err(malformed()),
         }
-        panic!("Should not end here for {}", s);
     }
 }
 
 type Stack = Vec<char>;
 
-fn parse_stacks(s: Vec<&str>) -> Vec<Stack> {
-    let indices: Vec<usize> = s
-        .first()
-        .unwrap()
-        .chars()
-        .enumerate()
-        .filter(|(_, c)| c.is_numeric())
-        .map(|(i, _)| i)
-        .collect();
-    let mut stacks = vec![Stack::new(); indices.len()];
-    for l in s.iter().skip(1).map(|l| l.chars().collect::<Vec<char>>()) {
-        for (i, stack) in stacks.iter_mut().enumerate() {
-            let str_i = indices.get(i).unwrap();
-            let c = l.get(*str_i).unwrap();
-            if c.is_alphabetic() {
-                stack.push(*c);
+/// Stacks keyed by the label printed under them, in drawing order.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Stacks {
+    labels: Vec<String>,
+    stacks: Vec<Stack>,
+}
+
+impl Stacks {
+    /// Each crate belongs to the label whose text is closest to its letter, so labels
+    /// wider than one character (`10`, `11`, ...) work too.
+    fn parse(drawing: &str) -> Result<Stacks, CrateError> {
+        let mut lines = drawing.lines().collect::<Vec<_>>();
+        let label_line = lines.pop().ok_or(CrateError::MissingLabels)?;
+        let mut labels = vec![];
+        let mut spans = vec![];
+        let mut start = None;
+        for (i, c) in label_line.char_indices().chain([(label_line.len(), ' ')]) {
+            match (start, c.is_whitespace()) {
+                (None, false) => start = Some(i),
+                (Some(s), true) => {
+                    labels.push(label_line[s..i].to_string());
+                    spans.push((s, i));
+                    start = None;
+                }
+                _ => {}
             }
         }
+        if labels.is_empty() {
+            return Err(CrateError::MissingLabels);
+        }
+        let mut stacks = vec![Stack::new(); labels.len()];
+        for line in lines.iter().rev() {
+            for (i, c) in line.char_indices().filter(|(_, c)| c.is_alphanumeric()) {
+                let nearest = spans
+                    .iter()
+                    .enumerate()
+                    .min_by_key(|(_, (s, e))| {
+                        if i < *s {
+                            s - i
+                        } else {
+                            (i + 1).saturating_sub(*e)
+                        }
+                    })
+                    .map(|(n, _)| n)
+                    .unwrap();
+                stacks[nearest].push(c);
+            }
+        }
+        Ok(Stacks { labels, stacks })
     }
-    stacks
-}
 
-fn parse(data: &str) -> (Vec<Move>, Vec<Stack>) {
-    let (stacks, moves) = data.split_once("\n\n").unwrap();
-    let mut stack_data_rev: Vec<&str> = stacks.split("\n").collect();
-    stack_data_rev.reverse();
-    let moves_vec = moves.lines().map(Move::from).collect();
-    let parsed_stacks = parse_stacks(stack_data_rev);
-    return (moves_vec, parsed_stacks);
+    fn index_of(&self, label: &str) -> Result<usize, CrateError> {
+        self.labels
+            .iter()
+            .position(|l| l == label)
+            .ok_or_else(|| CrateError::UnknownStack(label.to_string()))
+    }
+
+    fn tops(&self) -> String {
+        self.stacks
+            .iter()
+            .filter_map(|stack| stack.last())
+            .collect()
+    }
+
+    /// Draws the stacks the way the puzzle input does, one label-wide column each.
+    fn render(&self) -> String {
+        let width = self
+            .labels
+            .iter()
+            .map(|l| l.len())
+            .max()
+            .unwrap_or(0)
+            .max(3);
+        let height = self.stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+        let cell = |s: &str| format!("{:^width$}", s, width = width);
+        let mut rows = (0..height)
+            .rev()
+            .map(|level| {
+                self.stacks
+                    .iter()
+                    .map(|s| s.get(level).map_or(cell(""), |c| cell(&format!("[{}]", c))))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect::<Vec<_>>();
+        rows.push(
+            self.labels
+                .iter()
+                .map(|l| cell(l))
+                .collect::<Vec<_>>()
+                .join(" "),
+        );
+        rows.join("\n")
+    }
 }
 
-fn do_moves_p1(moves: Vec<Move>, stacks: &mut Vec<Stack>) {
-    for m in moves.iter() {
-        let mut items = Vec::new();
-        {
-            let from = stacks.get_mut(m.from).unwrap();
-            items = from.split_off(from.len() - (m.count as usize));
+/// A crane moves crates `batch_size` at a time; each batch keeps its order, so a
+/// batch size of 1 reverses the moved crates and an unbounded one preserves them.
+trait Crane {
+    fn batch_size(&self, count: usize) -> usize;
+
+    fn apply(&self, stacks: &mut Stacks, m: &Move) -> Result<(), CrateError> {
+        let from = stacks.index_of(&m.from)?;
+        let to = stacks.index_of(&m.to)?;
+        let has = stacks.stacks[from].len();
+        if m.count > has {
+            return Err(CrateError::NotEnoughCrates {
+                label: m.from.clone(),
+                has,
+                wanted: m.count,
+            });
         }
-        {
-            let to = stacks.get_mut(m.to).unwrap();
-            to.extend(items.iter().rev());
+        let mut remaining = m.count;
+        while remaining > 0 {
+            let batch = self.batch_size(m.count).clamp(1, remaining);
+            let source = &mut stacks.stacks[from];
+            let items = source.split_off(source.len() - batch);
+            stacks.stacks[to].extend(items);
+            remaining -= batch;
         }
+        Ok(())
     }
 }
 
-fn do_moves_p2(moves: Vec<Move>, stacks: &mut Vec<Stack>) {
-    for m in moves.iter() {
-        let mut items = Vec::new();
-        {
-            let from = stacks.get_mut(m.from).unwrap();
-            items = from.split_off(from.len() - (m.count as usize));
-        }
-        {
-            let to = stacks.get_mut(m.to).unwrap();
-            to.extend(items);
-        }
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn batch_size(&self, _count: usize) -> usize {
+        1
     }
 }
 
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn batch_size(&self, count: usize) -> usize {
+        count
+    }
+}
+
+/// A crane that can lift at most a fixed number of crates at once.
+#[cfg(test)]
+struct BatchCrane(usize);
+
+#[cfg(test)]
+impl Crane for BatchCrane {
+    fn batch_size(&self, _count: usize) -> usize {
+        self.0
+    }
+}
+
+fn parse(data: &str) -> Result<(Vec<Move>, Stacks), CrateError> {
+    let (stacks, moves) = data.split_once("\n\n").ok_or(CrateError::MissingMoves)?;
+    let moves_vec = moves
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(Move::from)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok((moves_vec, Stacks::parse(stacks)?))
+}
+
+/// Applies every move, calling `after_each` with the stacks after each one.
+fn run(
+    crane: &dyn Crane,
+    stacks: &mut Stacks,
+    moves: &[Move],
+    mut after_each: impl FnMut(&Move, &Stacks),
+) -> Result<(), CrateError> {
+    for m in moves {
+        crane.apply(stacks, m)?;
+        after_each(m, stacks);
+    }
+    Ok(())
+}
+
+fn solve_with(data: &str, crane: &dyn Crane) -> String {
+    let (moves, mut stacks) = parse(data).unwrap();
+    run(crane, &mut stacks, &moves, |_, _| {}).unwrap();
+    stacks.tops()
+}
+
 fn solve1(data: &str) -> String {
-    let (moves, mut stacks) = parse(data);
-    do_moves_p1(moves, &mut stacks);
-    stacks
-        .iter()
-        .map(|stack| stack.last().unwrap())
-        .collect::<String>()
+    solve_with(data, &CrateMover9000)
 }
 
 fn solve2(data: &str) -> String {
-    let (moves, mut stacks) = parse(data);
-    do_moves_p2(moves, &mut stacks);
-    stacks
-        .iter()
-        .map(|stack| stack.last().unwrap())
-        .collect::<String>()
+    solve_with(data, &CrateMover9001)
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = parse(&String::from_utf8_lossy(data));
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    if std::env::args().any(|a| a == "--print-stacks") {
+        let (moves, mut stacks) = parse(data).unwrap();
+        println!("{}\n", stacks.render());
+        run(&CrateMover9000, &mut stacks, &moves, |m, s| {
+            println!(
+                "move {} from {} to {}\n{}\n",
+                m.count,
+                m.from,
+                m.to,
+                s.render()
+            )
+        })
+        .unwrap();
+    }
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -137,6 +284,81 @@ mod tests {
         assert_eq!(solve2(data), "LVZPSTTCZ")
     }
 
+    #[test]
+    fn test_render_round_trip() {
+        let data = include_str!("../example.txt");
+        let (drawing, _) = data.split_once("\n\n").unwrap();
+        assert_eq!(Stacks::parse(drawing).unwrap().render(), drawing);
+        let (moves, mut stacks) = parse(data).unwrap();
+        let mut frames = vec![];
+        run(&CrateMover9000, &mut stacks, &moves[..1], |_, s| {
+            frames.push(s.render())
+        })
+        .unwrap();
+        assert_eq!(
+            frames,
+            vec!["[D]        \n[N] [C]    \n[Z] [M] [P]\n 1   2   3 "]
+        );
+    }
+
+    #[test]
+    fn test_more_than_nine_stacks() {
+        let drawing = (1..=11)
+            .map(|n| format!("[{}]", (b'A' + n as u8 - 1) as char))
+            .collect::<Vec<_>>()
+            .join(" ")
+            + "\n"
+            + &(1..=11)
+                .map(|n| format!("{:^3}", n))
+                .collect::<Vec<_>>()
+                .join(" ");
+        let data = format!("{}\n\nmove 1 from 11 to 10\nmove 2 from 10 to 1", drawing);
+        assert_eq!(solve2(&data), "KBCDEFGHI");
+        let stacks = Stacks::parse(&drawing).unwrap();
+        assert_eq!(stacks.labels.last().unwrap(), "11");
+        assert_eq!(stacks.render(), drawing);
+    }
+
+    #[test]
+    fn test_batch_crane_and_validation() {
+        let (_, mut stacks) = parse(include_str!("../example.txt")).unwrap();
+        stacks.stacks[2] = vec!['A', 'B', 'C', 'D', 'E'];
+        let m = Move::from("move 5 from 3 to 1").unwrap();
+        BatchCrane(2).apply(&mut stacks, &m).unwrap();
+        assert_eq!(stacks.stacks[0], vec!['Z', 'N', 'D', 'E', 'B', 'C', 'A']);
+        assert_eq!(
+            CrateMover9000.apply(&mut stacks, &Move::from("move 4 from 2 to 1").unwrap()),
+            Err(CrateError::NotEnoughCrates {
+                label: "2".to_string(),
+                has: 3,
+                wanted: 4
+            })
+        );
+        assert_eq!(
+            CrateMover9000.apply(&mut stacks, &Move::from("move 1 from 4 to 1").unwrap()),
+            Err(CrateError::UnknownStack("4".to_string()))
+        );
+        assert!(Move::from("move x from 1 to 2").is_err());
+    }
+
+    #[test]
+    fn test_generated_drawings_round_trip() {
+        for seed in 0..20 {
+            let data = aoc::gen::crate_moves(&mut aoc::gen::Rng::new(seed), 40);
+            let (drawing, _) = data.split_once("\n\n").unwrap();
+            let (moves, stacks) = parse(&data).unwrap();
+            assert_eq!(stacks.render(), drawing, "seed {}", seed);
+            for crane in [&CrateMover9000 as &dyn Crane, &CrateMover9001] {
+                run(crane, &mut stacks.clone(), &moves, |_, _| {}).unwrap();
+            }
+        }
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
@@ -1,44 +1,161 @@
 #![feature(test)]
 extern crate test;
 
-use std::collections::HashSet;
+use std::collections::BTreeMap;
+use std::io::{self, Read};
 
 const PACKET_START_MARKER_SIZE: usize = 4;
 
-fn first_unique_window_ends_at(data: &str, window_size: usize) -> usize {
-    let data_slice = &data.chars().collect::<Vec<char>>()[..];
-    let data_windows = data_slice.windows(window_size);
-    let mut hs: HashSet<&char> = HashSet::with_capacity(window_size);
-    data_windows
-        .enumerate()
-        .find_map(|(i, chars)| {
-            hs.extend(chars.iter());
-            let hs_len = hs.len();
-            hs.clear();
-            match hs_len {
-                _ if hs_len == window_size => Some(i + window_size),
-                _ => None,
+/// A 256-bit mask over the last `size` bytes, one bit per byte value, flipped as a byte
+/// enters and again as it leaves, so a bit is set iff its byte occurs an odd number of
+/// times. `odd` counts the set bits; it can only reach `size` when every byte in the
+/// window occurs exactly once, which is where a marker ends.
+struct Window {
+    size: usize,
+    mask: [u64; 4],
+    odd: usize,
+}
+
+impl Window {
+    fn toggle(&mut self, byte: u8) {
+        let (word, bit) = (byte as usize / 64, 1u64 << (byte % 64));
+        self.mask[word] ^= bit;
+        if self.mask[word] & bit != 0 {
+            self.odd += 1;
+        } else {
+            self.odd -= 1;
+        }
+    }
+}
+
+/// Tracks any number of window sizes over one stream. Only the last `max(size)` bytes
+/// are kept, in a ring buffer, so the input can be of any length.
+struct MarkerDetector {
+    windows: Vec<Window>,
+    history: Vec<u8>,
+    position: usize,
+}
+
+impl MarkerDetector {
+    fn new(window_sizes: &[usize]) -> Self {
+        let longest = window_sizes.iter().copied().max().unwrap_or(0);
+        MarkerDetector {
+            windows: window_sizes
+                .iter()
+                .map(|size| Window {
+                    size: *size,
+                    mask: [0; 4],
+                    odd: 0,
+                })
+                .collect(),
+            history: vec![0; longest.max(1)],
+            position: 0,
+        }
+    }
+
+    /// Feeds one byte and calls `on_marker` with each window size whose marker ends at
+    /// this byte, as the 1-based count of bytes read so far.
+    fn push(&mut self, byte: u8, mut on_marker: impl FnMut(usize, usize)) {
+        let capacity = self.history.len();
+        for window in self.windows.iter_mut() {
+            if self.position >= window.size && window.size > 0 {
+                window.toggle(self.history[(self.position - window.size) % capacity]);
             }
-        })
-        .unwrap()
+            window.toggle(byte);
+        }
+        self.history[self.position % capacity] = byte;
+        self.position += 1;
+        for window in self.windows.iter() {
+            if window.size > 0 && window.odd == window.size {
+                on_marker(window.size, self.position);
+            }
+        }
+    }
+}
+
+fn for_each_byte<R: Read>(mut reader: R, mut f: impl FnMut(u8) -> bool) -> io::Result<()> {
+    let mut buf = [0u8; 8192];
+    loop {
+        let n = match reader.read(&mut buf) {
+            Ok(0) => return Ok(()),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        if !buf[..n].iter().all(|b| f(*b)) {
+            return Ok(());
+        }
+    }
+}
+
+/// End of the first window of `window_size` distinct bytes, or `None` if the stream
+/// has none. Stops reading as soon as the marker is found.
+fn first_marker<R: Read>(reader: R, window_size: usize) -> io::Result<Option<usize>> {
+    let mut detector = MarkerDetector::new(&[window_size]);
+    let mut found = None;
+    for_each_byte(reader, |b| {
+        detector.push(b, |_, end| found = Some(end));
+        found.is_none()
+    })?;
+    Ok(found)
+}
+
+/// Every marker end for each of `window_sizes`, found in a single pass.
+fn all_markers<R: Read>(
+    reader: R,
+    window_sizes: &[usize],
+) -> io::Result<BTreeMap<usize, Vec<usize>>> {
+    let mut detector = MarkerDetector::new(window_sizes);
+    let mut markers: BTreeMap<usize, Vec<usize>> =
+        window_sizes.iter().map(|size| (*size, vec![])).collect();
+    for_each_byte(reader, |b| {
+        detector.push(b, |size, end| markers.get_mut(&size).unwrap().push(end));
+        true
+    })?;
+    Ok(markers)
 }
 
 fn solve1(data: &str) -> usize {
-    first_unique_window_ends_at(data, PACKET_START_MARKER_SIZE)
+    first_marker(data.as_bytes(), PACKET_START_MARKER_SIZE)
+        .unwrap()
+        .expect("no start-of-packet marker")
 }
 
 const MESSAGE_START_MARKER_SIZE: usize = 14;
 
 fn solve2(data: &str) -> usize {
-    first_unique_window_ends_at(data, MESSAGE_START_MARKER_SIZE)
+    first_marker(data.as_bytes(), MESSAGE_START_MARKER_SIZE)
+        .unwrap()
+        .expect("no start-of-message marker")
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = first_marker(data, 14);
+    let _ = all_markers(data, &[0, 1, 4, 14, 300]);
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
+    if std::env::args().any(|a| a == "--all-markers") {
+        let sizes = [PACKET_START_MARKER_SIZE, MESSAGE_START_MARKER_SIZE];
+        for (size, ends) in all_markers(data.as_bytes(), &sizes).unwrap() {
+            println!(
+                "{} markers of size {}, first ending at {:?}",
+                ends.len(),
+                size,
+                ends.first()
+            );
+        }
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -55,6 +172,58 @@ mod tests {
         assert_eq!(solve2(data), 19)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 1235)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 3051)
+    }
+
+    #[test]
+    fn test_no_marker() {
+        assert_eq!(first_marker("aabbaabb".as_bytes(), 3).unwrap(), None);
+        assert_eq!(first_marker("abc".as_bytes(), 4).unwrap(), None);
+    }
+
+    #[test]
+    fn test_all_markers_for_several_sizes() {
+        let markers = all_markers("abcabbd".as_bytes(), &[2, 3]).unwrap();
+        assert_eq!(markers[&2], vec![2, 3, 4, 5, 7]);
+        assert_eq!(markers[&3], vec![3, 4, 5]);
+    }
+
+    #[test]
+    fn test_mask_matches_brute_force() {
+        let data = b"aaabaaacbbbd\xff\x00\xffxyzzyx\x80\x81\x82\x83";
+        let sizes = [1, 2, 3, 4, 5];
+        let markers = all_markers(&data[..], &sizes).unwrap();
+        for size in sizes {
+            let expected = data
+                .windows(size)
+                .enumerate()
+                .filter(|(_, w)| (0..size).all(|i| !w[i + 1..].contains(&w[i])))
+                .map(|(i, _)| i + size)
+                .collect::<Vec<_>>();
+            assert_eq!(markers[&size], expected, "size {}", size);
+        }
+    }
+
+    #[test]
+    fn test_stream_longer_than_buffer() {
+        let data = "ab".repeat(10_000) + "cd";
+        assert_eq!(first_marker(data.as_bytes(), 4).unwrap(), Some(20_002));
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
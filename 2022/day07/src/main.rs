@@ -1,8 +1,24 @@
 #![feature(test)]
 
-use std::{collections::HashMap, rc::Rc};
 extern crate test;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum OutputError {
+    UnknownCommand(String),
+    MalformedListing(String),
+    /// `$ cd /` anywhere but on the first line.
+    UnexpectedRoot,
+    /// `$ cd ..` out of the root directory.
+    LeftRoot,
+    SizeOverflow,
+    /// More than `MAX_DEPTH` directories deep.
+    TooDeep,
+}
+
+/// Deeper trees are rejected rather than risk overflowing the stack, both while
+/// building them and in the recursive walks and drop afterwards.
+const MAX_DEPTH: usize = 1000;
+
 #[derive(Debug)]
 enum OutputItem {
     Cd(String),
@@ -11,82 +27,158 @@ enum OutputItem {
     LsDirOutput(String),
 }
 
-struct FS {
-    parent: Option<Rc<FS>,
-    children: Rc<HashMap<String, FS>>,
-    size: usize,
-}
-´´
 impl OutputItem {
-    fn from(data: &str) -> OutputItem {
-        match (data.starts_with("$"), data.ends_with("ls")) {
-            (true, false) => OutputItem::Cd(data.split_once("cd").unwrap().1.trim().to_string()),
-            (true, true) => OutputItem::Ls,
-            (false, _) => {
-                println!("Data: {}", data);
-                let (size, filename) = data.split_once(" ").unwrap();
-                match size {
-                    "dir" => OutputItem::LsDirOutput(size.to_string()),
-                    _ => OutputItem::LsFileOutput(
-                        size.parse::<usize>().unwrap(),
-                        filename.to_string(),
-                    ),
-                }
-            }
+    fn from(data: &str) -> Result<OutputItem, OutputError> {
+        match data.split_ascii_whitespace().collect::<Vec<_>>()[..] {
+            ["$", "cd", dir] => Ok(OutputItem::Cd(dir.to_string())),
+            ["$", "ls"] => Ok(OutputItem::Ls),
+            ["$", ..] => Err(OutputError::UnknownCommand(data.to_string())),
+            ["dir", name] => Ok(OutputItem::LsDirOutput(name.to_string())),
+            [size, name] => size
+                .parse::<usize>()
+                .map(|size| OutputItem::LsFileOutput(size, name.to_string()))
+                .map_err(|_| OutputError::MalformedListing(data.to_string())),
+            _ => Err(OutputError::MalformedListing(data.to_string())),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// A directory holds its total size, files in it and below it included.
+#[derive(Debug, Clone, PartialEq, Eq)]
 enum FS {
-    Dir(String, Option<usize>, Vec<FS>),
+    Dir(String, usize, Vec<FS>),
     File(String, usize),
 }
 
 impl FS {
-    fn print(&self) {
+    fn size(&self) -> usize {
+        match self {
+            FS::Dir(_, size, _) | FS::File(_, size) => *size,
+        }
+    }
+
+    /// The tree in the puzzle's `- name (dir)` format, indented by `depth`.
+    fn print(&self, depth: usize) {
+        let indent = "  ".repeat(depth);
         match self {
             FS::Dir(name, size, contents) => {
-                println!("{} {:?}", name, size);
-                contents.iter().for_each(|fs| fs.print());
+                println!("{}- {} (dir, size={})", indent, name, size);
+                contents.iter().for_each(|fs| fs.print(depth + 1));
             }
-            FS::File(name, size) => print!("{} {}", name, size),
+            FS::File(name, size) => println!("{}- {} (file, size={})", indent, name, size),
+        }
+    }
+
+    /// Sizes of this directory and every directory below it.
+    fn dir_sizes(&self) -> Vec<usize> {
+        match self {
+            FS::Dir(_, size, contents) => std::iter::once(*size)
+                .chain(contents.iter().flat_map(|fs| fs.dir_sizes()))
+                .collect(),
+            FS::File(_, _) => vec![],
         }
     }
-    fn build(data: &str) {}
 }
 
-fn parse(data: &str) -> Vec<OutputItem> {
-    data.lines().map(OutputItem::from).collect()
+fn parse(data: &str) -> Result<Vec<OutputItem>, OutputError> {
+    data.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(OutputItem::from)
+        .collect()
 }
 
-fn build_fs(items: Vec<OutputItem>) -> FS {
-    let mut fs = FS::Dir("/".to_string(), None, Vec::new());
-    let mut curr = fs;
-    let mut previous = FS::Dir("".to_string(), None, vec![]);
-    for item in items[1..].iter() {
+/// Reads the listing of directory `name`, `depth` levels below the root, and of every
+/// directory entered from it, up to the `$ cd ..` that leaves it.
+fn build_dir(
+    name: String,
+    depth: usize,
+    items: &mut impl Iterator<Item = OutputItem>,
+) -> Result<FS, OutputError> {
+    if depth > MAX_DEPTH {
+        return Err(OutputError::TooDeep);
+    }
+    let mut contents = vec![];
+    while let Some(item) = items.next() {
         match item {
-            Cd(dirname) => matc,
+            OutputItem::Cd(dir) if dir == ".." => break,
+            OutputItem::Cd(dir) if dir == "/" => return Err(OutputError::UnexpectedRoot),
+            OutputItem::Cd(dir) => {
+                let entered = build_dir(dir.clone(), depth + 1, items)?;
+                match contents
+                    .iter_mut()
+                    .find(|fs| matches!(fs, FS::Dir(name, _, _) if *name == dir))
+                {
+                    Some(listed) => *listed = entered,
+                    None => contents.push(entered),
+                }
+            }
+            OutputItem::Ls => {}
+            OutputItem::LsDirOutput(dir) => contents.push(FS::Dir(dir, 0, vec![])),
+            OutputItem::LsFileOutput(size, file) => contents.push(FS::File(file, size)),
         }
     }
+    let size = contents
+        .iter()
+        .try_fold(0usize, |total, fs| total.checked_add(fs.size()))
+        .ok_or(OutputError::SizeOverflow)?;
+    Ok(FS::Dir(name, size, contents))
 }
 
+/// Replays the terminal output from the root directory. A directory that is listed
+/// but never entered counts as empty.
+fn build_fs(items: Vec<OutputItem>) -> Result<FS, OutputError> {
+    let mut items = items.into_iter().peekable();
+    items.next_if(|item| matches!(item, OutputItem::Cd(dir) if dir == "/"));
+    let root = build_dir("/".to_string(), 0, &mut items)?;
+    match items.next() {
+        Some(_) => Err(OutputError::LeftRoot),
+        None => Ok(root),
+    }
+}
+
+const SMALL_DIR_SIZE: usize = 100000;
+const DISK_SIZE: usize = 70000000;
+const SPACE_NEEDED: usize = 30000000;
+
 fn solve1(data: &str) -> usize {
-    let items = parse(data);
-    println!("{:?}", data);
-    0
+    let fs = parse(data).and_then(build_fs).unwrap();
+    fs.dir_sizes()
+        .into_iter()
+        .filter(|size| *size <= SMALL_DIR_SIZE)
+        .sum()
 }
 
+/// The size of the smallest directory that frees up enough space once deleted.
 fn solve2(data: &str) -> usize {
-    0
+    let fs = parse(data).and_then(build_fs).unwrap();
+    let to_free = (fs.size() + SPACE_NEEDED).saturating_sub(DISK_SIZE);
+    fs.dir_sizes()
+        .into_iter()
+        .filter(|size| *size >= to_free)
+        .min()
+        .unwrap()
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    if let Ok(fs) = parse(&String::from_utf8_lossy(data)).and_then(build_fs) {
+        fs.dir_sizes();
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
+    if std::env::args().any(|a| a == "--tree") {
+        parse(data).and_then(build_fs).unwrap().print(0);
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -97,10 +189,70 @@ mod tests {
         assert_eq!(solve1(data), 95437)
     }
 
+    #[test]
+    fn test_part1_regression() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 1443806)
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            OutputItem::from("dir ls"),
+            Ok(OutputItem::LsDirOutput(name)) if name == "ls"
+        ));
+        assert_eq!(
+            OutputItem::from("$ rm -rf /").unwrap_err(),
+            OutputError::UnknownCommand("$ rm -rf /".to_string())
+        );
+        assert_eq!(
+            OutputItem::from("12kb a.txt").unwrap_err(),
+            OutputError::MalformedListing("12kb a.txt".to_string())
+        );
+    }
+
     #[test]
     fn test_part2() {
         let data = include_str!("../example.txt");
-        assert_eq!(solve2(data), 230)
+        assert_eq!(solve2(data), 24933642)
+    }
+
+    #[test]
+    fn test_part2_regression() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 942298)
+    }
+
+    #[test]
+    fn test_build_fs() {
+        let items = parse("$ cd /\n$ ls\ndir a\n10 b\n$ cd a\n$ ls\n5 c").unwrap();
+        assert_eq!(
+            build_fs(items),
+            Ok(FS::Dir(
+                "/".to_string(),
+                15,
+                vec![
+                    FS::Dir("a".to_string(), 5, vec![FS::File("c".to_string(), 5)]),
+                    FS::File("b".to_string(), 10),
+                ]
+            ))
+        );
+        let build = |data: &str| parse(data).and_then(build_fs);
+        assert_eq!(build("$ cd a\n$ cd /"), Err(OutputError::UnexpectedRoot));
+        assert_eq!(build("$ cd ..\n$ ls"), Err(OutputError::LeftRoot));
+        assert_eq!(
+            build(&format!("{} a\n1 b", usize::MAX)),
+            Err(OutputError::SizeOverflow)
+        );
+        let deep = "$ cd a\n".repeat(MAX_DEPTH);
+        assert!(build(&deep).is_ok());
+        let deeper = deep + "$ cd a";
+        assert_eq!(build(&deeper), Err(OutputError::TooDeep));
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
     }
 
     #[bench]
@@ -4,83 +4,155 @@ extern crate test;
 #[derive(Debug, Clone, Copy)]
 struct Tree {
     height: i16,
-    visible: bool,
 }
 
 type Forest = Vec<Vec<Tree>>;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ForestError {
+    Empty,
+    InvalidHeight(char),
+    RaggedRow(usize),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edge {
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+const EDGES: [Edge; 4] = [Edge::Left, Edge::Right, Edge::Top, Edge::Bottom];
+
+/// The edges a tree can be seen from, one bit per `Edge`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+struct Visibility(u8);
+
+impl Visibility {
+    fn seen_from(&self, edge: Edge) -> bool {
+        self.0 & (1 << edge as u8) != 0
+    }
+
+    fn is_visible(&self) -> bool {
+        self.0 != 0
+    }
+}
+
+/// For each position, how far one can see towards lower indices: up to and including
+/// the first tree at least as tall, or to the edge. Trees that are hidden behind a
+/// taller one can never block a later view, so a stack of strictly decreasing heights
+/// is enough and every index is pushed and popped at most once.
+fn viewing_distances(heights: &[i16]) -> Vec<usize> {
+    let mut stack: Vec<usize> = vec![];
+    heights
+        .iter()
+        .enumerate()
+        .map(|(i, h)| {
+            while stack.last().is_some_and(|j| heights[*j] < *h) {
+                stack.pop();
+            }
+            let distance = stack.last().map_or(i, |j| i - j);
+            stack.push(i);
+            distance
+        })
+        .collect()
+}
+
 trait ForestMethods {
-    fn mark_visible_trees(&mut self);
+    fn lines(&self, edge: Edge) -> Vec<Vec<(usize, usize)>>;
+    fn visibility(&self) -> Vec<Vec<Visibility>>;
+    fn scenic_scores(&self) -> Vec<Vec<usize>>;
     fn count_visible_trees(&self) -> usize;
+    fn visible_from(&self, edge: Edge) -> Vec<(usize, usize)>;
+    /// Walks the four rays from one tree; the tests check the sweeps against it.
     fn get_scenic_score(&self, x: usize, y: usize) -> usize;
     fn max_scenic_score(&self) -> usize;
-    fn print(&self);
+    fn top_scenic_trees(&self, k: usize) -> Vec<((usize, usize), usize)>;
     fn get_xy(&self, x: usize, y: usize) -> &Tree;
 }
 
 impl ForestMethods for Forest {
-    fn print(&self) {
-        let rows = self.iter().map(|l| {
-            l.iter()
-                .map(|t| if t.visible { "X" } else { "0" })
-                .collect::<String>()
-        });
-        rows.enumerate()
-            .for_each(|(i, r)| println!("{:0>2} {} {:0>2}", i, r, i))
-    }
-    fn mark_visible_trees(&mut self) {
-        let rows = self.iter().len();
-        let columns = self.iter().next().unwrap().len();
-        for y in 0..rows {
-            let mut last_max = -1;
-            for x in 0..columns {
-                let mut tree = self.get_mut(y).unwrap().get_mut(x).unwrap();
-                if tree.height > last_max {
-                    last_max = tree.height;
-                    tree.visible = true;
-                }
-            }
+    /// Every row or column as `(x, y)` positions, ordered starting from `edge`.
+    fn lines(&self, edge: Edge) -> Vec<Vec<(usize, usize)>> {
+        let rows = self.len();
+        let columns = self.first().map_or(0, |r| r.len());
+        match edge {
+            Edge::Left => (0..rows)
+                .map(|y| (0..columns).map(|x| (x, y)).collect())
+                .collect(),
+            Edge::Right => (0..rows)
+                .map(|y| (0..columns).rev().map(|x| (x, y)).collect())
+                .collect(),
+            Edge::Top => (0..columns)
+                .map(|x| (0..rows).map(|y| (x, y)).collect())
+                .collect(),
+            Edge::Bottom => (0..columns)
+                .map(|x| (0..rows).rev().map(|y| (x, y)).collect())
+                .collect(),
         }
+    }
 
-        for y in 0..rows {
-            let mut last_max = -1;
-            for x in (0..columns).rev() {
-                let mut tree = self.get_mut(y).unwrap().get_mut(x).unwrap();
-                if tree.height > last_max {
-                    last_max = tree.height;
-                    tree.visible = true;
+    fn visibility(&self) -> Vec<Vec<Visibility>> {
+        let mut visibility = self
+            .iter()
+            .map(|r| vec![Visibility::default(); r.len()])
+            .collect::<Vec<_>>();
+        for edge in EDGES {
+            for line in self.lines(edge) {
+                let mut last_max = -1;
+                for (x, y) in line {
+                    let height = self.get_xy(x, y).height;
+                    if height > last_max {
+                        last_max = height;
+                        visibility[y][x].0 |= 1 << edge as u8;
+                    }
                 }
             }
         }
+        visibility
+    }
 
-        for x in 0..columns {
-            let mut last_max = -1;
-            for y in 0..rows {
-                let mut tree = self.get_mut(y).unwrap().get_mut(x).unwrap();
-                if tree.height > last_max {
-                    last_max = tree.height;
-                    tree.visible = true;
-                }
-            }
-        }
-        for x in 0..columns {
-            let mut last_max = -1;
-            for y in (0..rows).rev() {
-                let mut tree = self.get_mut(y).unwrap().get_mut(x).unwrap();
-                if tree.height > last_max {
-                    last_max = tree.height;
-                    tree.visible = true;
+    fn scenic_scores(&self) -> Vec<Vec<usize>> {
+        let mut scores = self
+            .iter()
+            .map(|r| vec![1usize; r.len()])
+            .collect::<Vec<_>>();
+        for edge in EDGES {
+            for line in self.lines(edge) {
+                // Distances towards `edge` come from scanning the line away from it.
+                let heights = line
+                    .iter()
+                    .map(|(x, y)| self.get_xy(*x, *y).height)
+                    .collect::<Vec<_>>();
+                for ((x, y), d) in line.iter().zip(viewing_distances(&heights)) {
+                    scores[*y][*x] *= d;
                 }
             }
         }
+        scores
     }
 
     fn count_visible_trees(&self) -> usize {
-        self.iter()
-            .map(|r| r.iter().filter(|t| t.visible).count())
+        self.visibility()
+            .iter()
+            .map(|r| r.iter().filter(|v| v.is_visible()).count())
             .sum()
     }
 
+    fn visible_from(&self, edge: Edge) -> Vec<(usize, usize)> {
+        self.visibility()
+            .iter()
+            .enumerate()
+            .flat_map(|(y, r)| {
+                r.iter()
+                    .enumerate()
+                    .filter(|(_, v)| v.seen_from(edge))
+                    .map(move |(x, _)| (x, y))
+            })
+            .collect()
+    }
+
     fn get_scenic_score(&self, x: usize, y: usize) -> usize {
         let own_height = self.get_xy(x, y).height;
         let accumulator_f = |(count, stop), t: &Tree| {
@@ -123,24 +195,27 @@ impl ForestMethods for Forest {
             .rev()
             .fold((0usize, false), accumulator_f)
             .0;
-        let score = right * left * down * up;
-        score
+        right * left * down * up
     }
 
     fn max_scenic_score(&self) -> usize {
-        let rows = self.iter().len();
-        let columns = self.iter().next().unwrap().len();
-        let mut current_max = 0;
-        for y in 1..rows - 1 {
-            for x in 1..columns - 1 {
-                let score = self.get_scenic_score(x, y);
-                if score > current_max {
-                    current_max = score;
-                }
-            }
-        }
+        self.scenic_scores()
+            .iter()
+            .flat_map(|r| r.iter().copied())
+            .max()
+            .unwrap_or(0)
+    }
 
-        current_max
+    fn top_scenic_trees(&self, k: usize) -> Vec<((usize, usize), usize)> {
+        let mut trees = self
+            .scenic_scores()
+            .iter()
+            .enumerate()
+            .flat_map(|(y, r)| r.iter().enumerate().map(move |(x, s)| ((x, y), *s)))
+            .collect::<Vec<_>>();
+        trees.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        trees.truncate(k);
+        trees
     }
 
     fn get_xy(&self, x: usize, y: usize) -> &Tree {
@@ -148,37 +223,103 @@ impl ForestMethods for Forest {
     }
 }
 
-fn parse(data: &str) -> Forest {
-    let zero_ascii = '0' as i16;
-    data.lines()
+/// Hidden trees in greys and visible ones in greens, brighter for taller trees.
+fn visibility_palette() -> Vec<aoc::image::Rgb> {
+    (0..10u8)
+        .map(|h| [30 + h * 12, 30 + h * 12, 30 + h * 12])
+        .chain((0..10u8).map(|h| [20, 90 + h * 16, 40 + h * 6]))
+        .collect()
+}
+
+fn visibility_frame(forest: &Forest) -> aoc::image::Frame {
+    let visibility = forest.visibility();
+    aoc::image::Frame::from_fn(forest[0].len(), forest.len(), |x, y| {
+        let height = forest.get_xy(x, y).height.clamp(0, 9) as u8;
+        height + if visibility[y][x].is_visible() { 10 } else { 0 }
+    })
+}
+
+fn parse(data: &str) -> Result<Forest, ForestError> {
+    let forest = data
+        .lines()
         .map(|l| {
-            l.bytes()
-                .map(|c| Tree {
-                    height: c as i16 - zero_ascii,
-                    visible: false,
+            l.chars()
+                .map(|c| {
+                    c.to_digit(10)
+                        .map(|h| Tree { height: h as i16 })
+                        .ok_or(ForestError::InvalidHeight(c))
                 })
-                .collect()
+                .collect::<Result<Vec<_>, _>>()
         })
-        .collect()
+        .collect::<Result<Forest, _>>()?;
+    let width = forest.first().map_or(0, |row| row.len());
+    if width == 0 {
+        return Err(ForestError::Empty);
+    }
+    match forest.iter().position(|row| row.len() != width) {
+        Some(line) => Err(ForestError::RaggedRow(line)),
+        None => Ok(forest),
+    }
 }
 
 fn solve1(data: &str) -> usize {
-    let mut parsed = parse(data);
-    parsed.mark_visible_trees();
+    let parsed = parse(data).unwrap();
     parsed.count_visible_trees()
 }
 
 fn solve2(data: &str) -> usize {
-    let parsed = parse(data);
+    let parsed = parse(data).unwrap();
     parsed.max_scenic_score()
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    if let Ok(forest) = parse(&String::from_utf8_lossy(data)) {
+        forest.count_visible_trees();
+        forest.max_scenic_score();
+    }
+}
+
+/// How many trees `--top-scenic [N]` asks for, five when `N` is left out.
+fn top_scenic_arg(args: impl IntoIterator<Item = String>) -> Option<usize> {
+    let mut args = args.into_iter().peekable();
+    while let Some(arg) = args.next() {
+        if arg == "--top-scenic" {
+            let k = args.peek().and_then(|n| n.parse().ok());
+            if k.is_some() {
+                args.next();
+            }
+            return Some(k.unwrap_or(5));
+        }
+    }
+    None
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
+    let forest = parse(data).unwrap();
+    let seen = EDGES.map(|edge| forest.visible_from(edge).len());
+    println!("Visible from left/right/top/bottom: {:?}", seen);
+    if let Some(k) = top_scenic_arg(std::env::args().skip(1)) {
+        for ((x, y), score) in forest.top_scenic_trees(k) {
+            debug_assert_eq!(forest.get_scenic_score(x, y), score);
+            println!("Tree at ({}, {}) scores {}", x, y, score);
+        }
+    }
+    if let Some(mut sink) =
+        aoc::sink::from_args(&visibility_palette(), "0000000000XXXXXXXXXX", 4, 1.0)
+    {
+        sink.push(&visibility_frame(&parse(data).unwrap())).unwrap();
+        sink.finish().unwrap();
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -193,7 +334,7 @@ mod tests {
     fn test_part2() {
         let data = include_str!("../example.txt");
         assert_eq!(solve2(data), 8);
-        let parsed = parse(data);
+        let parsed = parse(data).unwrap();
         assert_eq!(
             parsed.get_scenic_score(2, 3),
             8,
@@ -206,6 +347,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 1843)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 180000)
+    }
+
+    #[test]
+    fn test_scenic_scores_match_ray_walk() {
+        let parsed = parse(include_str!("../example.txt")).unwrap();
+        let scores = parsed.scenic_scores();
+        for (y, row) in scores.iter().enumerate() {
+            for (x, score) in row.iter().enumerate() {
+                assert_eq!(*score, parsed.get_scenic_score(x, y), "at {}, {}", x, y);
+            }
+        }
+        assert_eq!(parsed.top_scenic_trees(2), vec![((2, 3), 8), ((1, 2), 6)]);
+    }
+
+    #[test]
+    fn test_visible_from_edge() {
+        let parsed = parse(include_str!("../example.txt")).unwrap();
+        assert_eq!(
+            parsed.visible_from(Edge::Top),
+            vec![
+                (0, 0),
+                (1, 0),
+                (2, 0),
+                (3, 0),
+                (4, 0),
+                (1, 1),
+                (2, 1),
+                (0, 2),
+                (4, 3),
+                (3, 4)
+            ]
+        );
+        assert_eq!(viewing_distances(&[3, 0, 3, 7, 3]), vec![0, 1, 2, 3, 1]);
+    }
+
+    #[test]
+    fn test_visibility_frame() {
+        let forest = parse(include_str!("../example.txt")).unwrap();
+        let frame = visibility_frame(&forest);
+        assert_eq!((frame.width, frame.height), (5, 5));
+        assert_eq!(frame.pixels.iter().filter(|p| **p >= 10).count(), 21);
+        // The 5 in the middle of the second row is visible, the 1 after it is not.
+        assert_eq!(frame.get(2, 1), 15);
+        assert_eq!(frame.get(3, 1), 1);
+    }
+
+    #[test]
+    fn test_top_scenic_arg() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(top_scenic_arg(args(&["--stats"])), None);
+        assert_eq!(top_scenic_arg(args(&["--top-scenic", "3"])), Some(3));
+        assert_eq!(top_scenic_arg(args(&["--top-scenic", "--stats"])), Some(5));
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
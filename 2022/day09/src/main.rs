@@ -1,9 +1,8 @@
 #![feature(test)]
 
-use std::collections::HashSet;
 extern crate test;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Coordinate {
     y: i64,
     x: i64,
@@ -35,50 +34,265 @@ impl Coordinate {
         }
     }
 
-    fn is_in_same_row_or_column(&self, c: &Coordinate) -> bool {
-        self.x == c.x || self.y == c.y
+    /// Where this knot moves when following `leader`: nowhere while they touch,
+    /// otherwise one step along each axis on which they differ.
+    fn follow(&self, leader: &Coordinate) -> Coordinate {
+        if self.is_adjacent_to(leader) {
+            return *self;
+        }
+        Coordinate {
+            y: self.y + (leader.y - self.y).signum(),
+            x: self.x + (leader.x - self.x).signum(),
+        }
     }
+}
 
-    fn move_diagonally_towards(&self, c: &Coordinate) -> Coordinate {
-        assert!(!self.is_in_same_row_or_column(c));
-        match (self.y < c.y, self.x < c.x) {
-            (true, true) => Coordinate {
-                y: self.y + 1,
-                x: self.x + 1,
-            },
-            (true, false) => Coordinate {
-                y: self.y + 1,
-                x: self.x - 1,
-            },
-            (false, true) => Coordinate {
-                y: self.y - 1,
-                x: self.x + 1,
-            },
-            (false, false) => Coordinate {
-                y: self.y - 1,
-                x: self.x - 1,
-            },
+/// Cells a knot has been on, as a bitmap that grows to cover every new cell.
+#[derive(Debug, Clone)]
+struct VisitedGrid {
+    origin: Coordinate,
+    width: usize,
+    height: usize,
+    cells: Vec<bool>,
+    count: usize,
+}
+
+impl VisitedGrid {
+    fn new() -> Self {
+        VisitedGrid {
+            origin: Coordinate { y: 0, x: 0 },
+            width: 0,
+            height: 0,
+            cells: vec![],
+            count: 0,
         }
     }
 
-    fn move_directly_towards(&self, c: &Coordinate) -> Coordinate {
-        assert!(self.is_in_same_row_or_column(c));
-        if self.y == c.y && self.x == c.x {
-            return self.clone();
-        } else if self.y != c.y {
-            if self.y < c.y {
-                self.mv_once(&Movement::Up(1))
-            } else {
-                self.mv_once(&Movement::Down(1))
-            }
+    fn index(&self, c: &Coordinate) -> Option<usize> {
+        let y = usize::try_from(c.y - self.origin.y).ok()?;
+        let x = usize::try_from(c.x - self.origin.x).ok()?;
+        (y < self.height && x < self.width).then_some(y * self.width + x)
+    }
+
+    fn contains(&self, c: &Coordinate) -> bool {
+        self.index(c).is_some_and(|i| self.cells[i])
+    }
+
+    /// Grows to double the needed size along an axis, so inserting stays amortised O(1).
+    fn grow_to_include(&mut self, c: &Coordinate) {
+        let (min_y, max_y, min_x, max_x) = if self.cells.is_empty() {
+            (c.y, c.y, c.x, c.x)
+        } else {
+            let (top, right) = (
+                self.origin.y + self.height as i64 - 1,
+                self.origin.x + self.width as i64 - 1,
+            );
+            (
+                self.origin.y.min(c.y),
+                top.max(c.y),
+                self.origin.x.min(c.x),
+                right.max(c.x),
+            )
+        };
+        let (height, width) = ((max_y - min_y + 1) as usize, (max_x - min_x + 1) as usize);
+        let pad_y = if min_y < self.origin.y {
+            height as i64
+        } else {
+            0
+        };
+        let pad_x = if min_x < self.origin.x {
+            width as i64
         } else {
-            if self.x < c.x {
-                self.mv_once(&Movement::Right(1))
-            } else {
-                self.mv_once(&Movement::Left(1))
+            0
+        };
+        let mut grown = VisitedGrid {
+            origin: Coordinate {
+                y: min_y - pad_y,
+                x: min_x - pad_x,
+            },
+            height: height * 2,
+            width: width * 2,
+            cells: vec![false; height * width * 4],
+            count: self.count,
+        };
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.cells[y * self.width + x] {
+                    let c = Coordinate {
+                        y: self.origin.y + y as i64,
+                        x: self.origin.x + x as i64,
+                    };
+                    let i = grown.index(&c).unwrap();
+                    grown.cells[i] = true;
+                }
             }
         }
+        *self = grown;
+    }
+
+    fn insert(&mut self, c: &Coordinate) {
+        if self.index(c).is_none() {
+            self.grow_to_include(c);
+        }
+        let i = self.index(c).unwrap();
+        if !self.cells[i] {
+            self.cells[i] = true;
+            self.count += 1;
+        }
     }
+
+    fn count(&self) -> usize {
+        self.count
+    }
+
+    fn iter(&self) -> impl Iterator<Item = Coordinate> + '_ {
+        self.cells
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| **v)
+            .map(|(i, _)| Coordinate {
+                y: self.origin.y + (i / self.width) as i64,
+                x: self.origin.x + (i % self.width) as i64,
+            })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Rope {
+    knots: Vec<Coordinate>,
+    visited: Vec<VisitedGrid>,
+}
+
+impl Rope {
+    fn new(knot_count: usize) -> Self {
+        assert!(knot_count > 0, "a rope needs at least one knot");
+        let knots = vec![Coordinate { y: 0, x: 0 }; knot_count];
+        let mut visited = vec![VisitedGrid::new(); knot_count];
+        visited.iter_mut().for_each(|v| v.insert(&knots[0]));
+        Rope { knots, visited }
+    }
+
+    fn tail(&self) -> usize {
+        self.knots.len() - 1
+    }
+
+    fn step(&mut self, m: &Movement) {
+        self.knots[0] = self.knots[0].mv_once(m);
+        for i in 1..self.knots.len() {
+            self.knots[i] = self.knots[i].follow(&self.knots[i - 1]);
+        }
+        for (knot, visited) in self.knots.iter().zip(self.visited.iter_mut()) {
+            visited.insert(knot);
+        }
+    }
+
+    /// Applies `movements` one step at a time, yielding the knots after each step.
+    fn steps<'a>(
+        &'a mut self,
+        movements: &'a [Movement],
+    ) -> impl Iterator<Item = Vec<Coordinate>> + 'a {
+        movements
+            .iter()
+            .flat_map(|m| std::iter::repeat_n(m, m.count()))
+            .map(move |m| {
+                self.step(m);
+                self.knots.clone()
+            })
+    }
+
+    fn visited(&self, knot: usize) -> &VisitedGrid {
+        &self.visited[knot]
+    }
+
+    /// Draws the rope over the cells `visited_by` has been on, in the puzzle's style:
+    /// `H` for the head, knot numbers after it, `s` for the start and `#` for visited
+    /// cells, with the highest row first.
+    fn render(&self, visited_by: usize) -> String {
+        let visited = self.visited(visited_by);
+        let (min_y, max_y, min_x, max_x) = self
+            .knots
+            .iter()
+            .copied()
+            .chain(visited.iter())
+            .fold((0, 0, 0, 0), |(y0, y1, x0, x1), c| {
+                (y0.min(c.y), y1.max(c.y), x0.min(c.x), x1.max(c.x))
+            });
+        (min_y..=max_y)
+            .rev()
+            .map(|y| {
+                (min_x..=max_x)
+                    .map(|x| {
+                        let c = Coordinate { y, x };
+                        match self.knots.iter().position(|k| *k == c) {
+                            Some(0) => 'H',
+                            Some(i) if i < 10 => char::from_digit(i as u32, 10).unwrap(),
+                            Some(_) => '+',
+                            None if y == 0 && x == 0 => 's',
+                            None if visited.contains(&c) => '#',
+                            None => '.',
+                        }
+                    })
+                    .collect::<String>()
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+const ROPE_PALETTE: [aoc::image::Rgb; 5] = [
+    [20, 20, 30],
+    [60, 110, 160],
+    [240, 200, 60],
+    [200, 200, 200],
+    [230, 60, 60],
+];
+
+/// Passes `on_frame` the rope after every movement, or every few movements so there
+/// are at most `max_frames`, over the cells the tail has visited. The head's path bounds every
+/// frame, as the other knots never leave its bounding box.
+fn rope_frames(
+    movements: &[Movement],
+    knot_count: usize,
+    max_frames: usize,
+    mut on_frame: impl FnMut(aoc::image::Frame),
+) {
+    let mut rope = Rope::new(knot_count);
+    rope.steps(movements).for_each(drop);
+    let (min_y, max_y, min_x, max_x) = rope
+        .visited(0)
+        .iter()
+        .fold((0, 0, 0, 0), |(y0, y1, x0, x1), c| {
+            (y0.min(c.y), y1.max(c.y), x0.min(c.x), x1.max(c.x))
+        });
+    let (width, height) = ((max_x - min_x + 1) as usize, (max_y - min_y + 1) as usize);
+    let stride = movements.len().div_ceil(max_frames.max(1)).max(1);
+    let mut rope = Rope::new(knot_count);
+    for (i, m) in movements.iter().enumerate() {
+        (0..m.count()).for_each(|_| rope.step(m));
+        if (i + 1) % stride != 0 && i + 1 != movements.len() {
+            continue;
+        }
+        let frame = aoc::image::Frame::from_fn(width, height, |x, y| {
+            let c = Coordinate {
+                y: max_y - y as i64,
+                x: min_x + x as i64,
+            };
+            match rope.knots.iter().position(|k| *k == c) {
+                Some(0) => 4,
+                Some(_) => 3,
+                None if c.y == 0 && c.x == 0 => 2,
+                None if rope.visited(rope.tail()).contains(&c) => 1,
+                None => 0,
+            }
+        });
+        on_frame(frame);
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MovementError {
+    Malformed(String),
+    UnknownDirection(String),
 }
 
 enum Movement {
@@ -89,14 +303,17 @@ enum Movement {
 }
 
 impl Movement {
-    fn from(data: &str) -> Movement {
-        let (dir, count) = data.split_once(" ").unwrap();
+    fn from(data: &str) -> Result<Movement, MovementError> {
+        let (dir, count) = data
+            .split_once(" ")
+            .and_then(|(dir, count)| Some((dir, count.parse::<usize>().ok()?)))
+            .ok_or_else(|| MovementError::Malformed(data.to_string()))?;
         match dir {
-            "R" => Movement::Right(count.parse::<usize>().unwrap()),
-            "L" => Movement::Left(count.parse::<usize>().unwrap()),
-            "U" => Movement::Up(count.parse::<usize>().unwrap()),
-            "D" => Movement::Down(count.parse::<usize>().unwrap()),
-            _ => panic!("Should not be here, received {}", dir),
+            "R" => Ok(Movement::Right(count)),
+            "L" => Ok(Movement::Left(count)),
+            "U" => Ok(Movement::Up(count)),
+            "D" => Ok(Movement::Down(count)),
+            _ => Err(MovementError::UnknownDirection(dir.to_string())),
         }
     }
     fn count(&self) -> usize {
@@ -109,63 +326,51 @@ impl Movement {
     }
 }
 
+fn parse(data: &str) -> Result<Vec<Movement>, MovementError> {
+    data.lines().map(Movement::from).collect()
+}
+
+fn simulate(data: &str, knot_count: usize) -> usize {
+    let movements = parse(data).unwrap();
+    let mut rope = Rope::new(knot_count);
+    rope.steps(&movements).for_each(drop);
+    rope.visited(rope.tail()).count()
+}
+
 fn solve1(data: &str) -> usize {
-    let movements = data.lines().map(Movement::from);
-    let mut tail_set: HashSet<Coordinate> = HashSet::new();
-    let mut tail = Coordinate { y: 0, x: 0 };
-    let mut head = Coordinate { y: 0, x: 0 };
-    for movement in movements {
-        for _ in 0..movement.count() {
-            head = head.mv_once(&movement);
-            if !tail.is_adjacent_to(&head) {
-                if tail.is_in_same_row_or_column(&head) {
-                    tail = tail.move_directly_towards(&head);
-                } else {
-                    tail = tail.move_diagonally_towards(&head);
-                }
-            }
-            tail_set.insert(tail.clone());
-        }
-    }
-    tail_set.len()
+    simulate(data, 2)
 }
 
 fn solve2(data: &str) -> usize {
-    let movements = data.lines().map(Movement::from);
-    let mut tail_set: HashSet<Coordinate> = HashSet::new();
-    let mut knots = vec![Coordinate { y: 0, x: 0 }; 10];
-    for movement in movements {
-        for _ in 0..movement.count() {
-            let mut previous: Option<Coordinate> = None;
-            for knot in knots.iter_mut() {
-                if let Some(ref prev_knot) = previous {
-                    if !knot.is_adjacent_to(prev_knot) {
-                        if knot.is_in_same_row_or_column(&prev_knot) {
-                            *knot = knot.move_directly_towards(&prev_knot);
-                        } else {
-                            *knot = knot.move_diagonally_towards(&prev_knot);
-                        }
-                    }
-                    previous = Some(knot.clone());
-                } else {
-                    // head
-                    let old = knot.clone();
-                    *knot = knot.mv_once(&movement);
-                    previous = Some(knot.clone());
-                }
-            }
-            tail_set.insert(knots.last().unwrap().clone());
-        }
-    }
-    tail_set.len()
+    simulate(data, 10)
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = parse(&String::from_utf8_lossy(data));
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
+    if std::env::args().any(|a| a == "--print-rope") {
+        let mut rope = Rope::new(10);
+        rope.steps(&parse(data).unwrap()).for_each(drop);
+        println!("{}", rope.render(rope.tail()));
+    }
+    if let Some(mut sink) = aoc::sink::from_args(&ROPE_PALETTE, ".#skH", 2, 20.0) {
+        rope_frames(&parse(data).unwrap(), 10, 300, |frame| {
+            sink.push(&frame).unwrap()
+        });
+        sink.finish().unwrap();
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -197,11 +402,78 @@ mod tests {
     }
 
     #[test]
-    fn test_move_diagonally_towards() {
+    fn test_follow() {
         let target = Coordinate { y: 1, x: 4 };
         let start = Coordinate { y: 0, x: 2 };
         let expected = Coordinate { y: 1, x: 3 };
-        assert_eq!(start.move_diagonally_towards(&target), expected);
+        assert_eq!(start.follow(&target), expected);
+        let straight = Coordinate { y: 1, x: 2 };
+        assert_eq!(straight.follow(&target), Coordinate { y: 1, x: 3 });
+        assert_eq!(expected.follow(&target), expected);
+    }
+
+    #[test]
+    fn test_per_knot_visited_and_states() {
+        let movements = parse(include_str!("../example.txt")).unwrap();
+        let mut rope = Rope::new(3);
+        let states = rope.steps(&movements).collect::<Vec<_>>();
+        assert_eq!(states.len(), 24);
+        assert_eq!(states[3][0], Coordinate { y: 0, x: 4 });
+        assert_eq!(states[3][2], Coordinate { y: 0, x: 2 });
+        assert_eq!(rope.visited(1).count(), 13);
+        assert!(rope.visited(0).count() > rope.visited(2).count());
+    }
+
+    #[test]
+    fn test_render() {
+        let mut rope = Rope::new(2);
+        rope.steps(&[Movement::Right(3), Movement::Up(1)])
+            .for_each(drop);
+        assert_eq!(rope.render(1), "...H\ns#1.");
+    }
+
+    #[test]
+    fn test_rope_frames() {
+        let movements = parse(include_str!("../example2.txt")).unwrap();
+        let mut frames = vec![];
+        rope_frames(&movements, 10, 4, |f| frames.push(f));
+        assert_eq!(frames.len(), 4);
+        let last = frames.last().unwrap();
+        // The head's path spans x -11..=14 and y -5..=15 and ends at x -11, y 15,
+        // the top left corner.
+        assert_eq!((last.width, last.height), (26, 21));
+        assert_eq!(last.get(0, 0), 4);
+        assert_eq!(last.pixels.iter().filter(|p| **p == 4).count(), 1);
+    }
+
+    #[test]
+    fn test_mirrored_movements_visit_as_many_cells() {
+        for seed in 0..20 {
+            let data = aoc::gen::rope_movements(&mut aoc::gen::Rng::new(seed), 100);
+            let mirrored = data
+                .chars()
+                .map(|c| match c {
+                    'U' => 'D',
+                    'D' => 'U',
+                    'L' => 'R',
+                    'R' => 'L',
+                    c => c,
+                })
+                .collect::<String>();
+            assert_eq!(solve1(&data), solve1(&mirrored), "seed {}", seed);
+            assert_eq!(solve2(&data), solve2(&mirrored), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(
+            fuzz_parse,
+            &[
+                include_bytes!("../example.txt"),
+                include_bytes!("../example2.txt"),
+            ],
+        );
     }
 
     #[bench]
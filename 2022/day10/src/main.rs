@@ -1,104 +1,256 @@
 #![feature(test)]
 
-use std::collections::HashMap;
 extern crate test;
 
-enum Operation {
-    Addx(i64),
-    Noop,
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Registers {
+    x: i64,
 }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-enum Pixel {
-    Dark,
-    Lit,
+impl Default for Registers {
+    fn default() -> Self {
+        Registers { x: 1 }
+    }
 }
 
-impl Operation {
-    fn from(s: &str) -> Operation {
-        if s.trim() == "noop" {
-            Operation::Noop
-        } else {
-            let (_, n) = s.split_once(" ").unwrap();
-            Operation::Addx(n.parse::<i64>().unwrap())
+/// An instruction takes `arity` arguments and `cycles` cycles and applies `effect`
+/// once its last cycle is done, so registers read during any of its cycles still hold
+/// the old values.
+#[derive(Clone, Copy)]
+struct Instruction {
+    name: &'static str,
+    arity: usize,
+    cycles: usize,
+    effect: fn(&mut Registers, &[i64]),
+}
+
+const ELF_CPU: [Instruction; 2] = [
+    Instruction {
+        name: "noop",
+        arity: 0,
+        cycles: 1,
+        effect: |_, _| {},
+    },
+    Instruction {
+        name: "addx",
+        arity: 1,
+        cycles: 2,
+        effect: |r, args| r.x = r.x.wrapping_add(args[0]),
+    },
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ProgramError {
+    UnknownInstruction(String),
+    BadArgument(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Operation {
+    instruction: usize,
+    args: Vec<i64>,
+}
+
+fn parse_program(
+    data: &str,
+    instruction_set: &[Instruction],
+) -> Result<Vec<Operation>, ProgramError> {
+    data.lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            let mut words = l.split_ascii_whitespace();
+            let name = words.next().unwrap();
+            let instruction = instruction_set
+                .iter()
+                .position(|i| i.name == name)
+                .ok_or_else(|| ProgramError::UnknownInstruction(l.to_string()))?;
+            let args = words
+                .map(|w| w.parse::<i64>())
+                .collect::<Result<Vec<_>, _>>()
+                .ok()
+                .filter(|args| args.len() == instruction_set[instruction].arity)
+                .ok_or_else(|| ProgramError::BadArgument(l.to_string()))?;
+            Ok(Operation { instruction, args })
+        })
+        .collect()
+}
+
+/// Registers as seen *during* a cycle, cycles counted from 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct CycleState {
+    cycle: usize,
+    registers: Registers,
+}
+
+type Hook<'a> = Box<dyn FnMut(&CycleState) + 'a>;
+
+struct Cpu<'a> {
+    instruction_set: &'a [Instruction],
+    program: &'a [Operation],
+    pc: usize,
+    elapsed_in_op: usize,
+    cycle: usize,
+    registers: Registers,
+    breakpoints: Vec<usize>,
+    hooks: Vec<(usize, Hook<'a>)>,
+}
+
+impl<'a> Cpu<'a> {
+    fn new(instruction_set: &'a [Instruction], program: &'a [Operation]) -> Self {
+        Cpu {
+            instruction_set,
+            program,
+            pc: 0,
+            elapsed_in_op: 0,
+            cycle: 0,
+            registers: Registers::default(),
+            breakpoints: vec![],
+            hooks: vec![],
         }
     }
-    fn get_cycles(self) -> usize {
-        match self {
-            Operation::Noop => 1,
-            Operation::Addx(_) => 2,
-        }
+
+    fn with_breakpoints(mut self, cycles: &[usize]) -> Self {
+        self.breakpoints.extend_from_slice(cycles);
+        self
     }
-}
 
-fn solve1(data: &str) -> i64 {
-    let ops = data.lines().map(Operation::from);
-    let mut cycle = 0usize;
-    let mut counter = 1i64;
-    let mut cycle_map: HashMap<usize, i64> = HashMap::with_capacity(240);
-    for op in ops {
-        match op {
-            Operation::Noop => {
-                cycle = cycle + 1;
-                cycle_map.insert(cycle, counter);
-            }
-            Operation::Addx(n) => {
-                for _ in 0..op.get_cycles() {
-                    cycle = cycle + 1;
-                    cycle_map.insert(cycle, counter);
-                }
-                counter = counter + n;
-            }
-        }
+    #[cfg(test)]
+    fn with_hook(mut self, cycle: usize, hook: impl FnMut(&CycleState) + 'a) -> Self {
+        self.hooks.push((cycle, Box::new(hook)));
+        self
+    }
+
+    /// Runs until the next cycle that has a breakpoint and returns its state.
+    fn run_to_breakpoint(&mut self) -> Option<CycleState> {
+        let breakpoints = std::mem::take(&mut self.breakpoints);
+        let hit = self.find(|s| breakpoints.contains(&s.cycle));
+        self.breakpoints = breakpoints;
+        hit
     }
-    let checkpoints: [usize; 6] = [20, 60, 100, 140, 180, 220];
-    checkpoints.iter().fold(0i64, |acc, cp| {
-        acc + ((*cp as i64) * cycle_map.get(cp).unwrap())
-    })
 }
 
-fn solve2(data: &str) -> String {
-    let ops = data.lines().map(Operation::from);
-    let mut cycle = 0usize;
-    let mut counter = 1i64;
-    let mut cycle_to_counter: Vec<(usize, i64)> = Vec::with_capacity(240);
-    for op in ops {
-        match op {
-            Operation::Noop => {
-                cycle += 1;
-                cycle_to_counter.push((cycle, counter));
-            }
-            Operation::Addx(n) => {
-                for _ in 0..2 {
-                    cycle += 1;
-                    cycle_to_counter.push((cycle, counter));
-                }
-                counter = counter + n;
-            }
+impl Iterator for Cpu<'_> {
+    type Item = CycleState;
+
+    fn next(&mut self) -> Option<CycleState> {
+        let op = self.program.get(self.pc)?;
+        let instruction = &self.instruction_set[op.instruction];
+        self.cycle += 1;
+        let state = CycleState {
+            cycle: self.cycle,
+            registers: self.registers,
+        };
+        self.hooks
+            .iter_mut()
+            .filter(|(cycle, _)| *cycle == state.cycle)
+            .for_each(|(_, hook)| hook(&state));
+        self.elapsed_in_op += 1;
+        if self.elapsed_in_op >= instruction.cycles {
+            (instruction.effect)(&mut self.registers, &op.args);
+            self.elapsed_in_op = 0;
+            self.pc += 1;
         }
+        Some(state)
     }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+enum Pixel {
+    Dark,
+    Lit,
+}
+
+fn signal_strength(cpu: &mut Cpu) -> i64 {
+    std::iter::from_fn(|| cpu.run_to_breakpoint())
+        .map(|s| s.cycle as i64 * s.registers.x)
+        .sum()
+}
+
+type Screen = [[Pixel; 40]; 6];
+
+/// The beam draws pixel `cycle - 1` and lights it when the 3 pixel wide sprite
+/// centred on `x` covers its column. `on_cycle` sees the screen after each cycle.
+fn draw_crt(
+    trace: impl Iterator<Item = CycleState>,
+    mut on_cycle: impl FnMut(&Screen, &CycleState),
+) -> Screen {
     let mut screen = [[Pixel::Dark; 40]; 6];
-    for (cycle, counter) in cycle_to_counter.iter() {
-        let column = (cycle - 1) % 40;
-        let row = (cycle - 1) / 40;
-        let column_i64 = column as i64;
-        if column_i64 >= counter - 1 && column_i64 <= counter + 1 {
+    for state in trace.take(240) {
+        let column = (state.cycle - 1) % 40;
+        let row = (state.cycle - 1) / 40;
+        if (column as i64).abs_diff(state.registers.x) <= 1 {
             screen[row][column] = Pixel::Lit;
         }
+        on_cycle(&screen, &state);
     }
+    screen
+}
 
-    let s = screen
+fn render_crt(trace: impl Iterator<Item = CycleState>) -> String {
+    draw_crt(trace, |_, _| {})
         .map(|r| r.map(|p| if p == Pixel::Dark { "." } else { "#" }).join(""))
-        .join("\n");
-    s
+        .join("\n")
+}
+
+const CRT_PALETTE: [aoc::image::Rgb; 4] =
+    [[15, 15, 35], [255, 255, 102], [70, 70, 110], [255, 80, 80]];
+
+/// The screen during `state`'s cycle: lit pixels, the sprite's three columns on the
+/// beam's row and the pixel being drawn.
+fn crt_frame(screen: &Screen, state: &CycleState) -> aoc::image::Frame {
+    let beam = state.cycle - 1;
+    aoc::image::Frame::from_fn(40, 6, |x, y| {
+        if y * 40 + x == beam {
+            3
+        } else if screen[y][x] == Pixel::Lit {
+            1
+        } else if y == beam / 40 && (x as i64).abs_diff(state.registers.x) <= 1 {
+            2
+        } else {
+            0
+        }
+    })
+}
+
+fn solve1(data: &str) -> i64 {
+    let program = parse_program(data, &ELF_CPU).unwrap();
+    let mut cpu = Cpu::new(&ELF_CPU, &program).with_breakpoints(&[20, 60, 100, 140, 180, 220]);
+    signal_strength(&mut cpu)
+}
+
+fn solve2(data: &str) -> String {
+    let program = parse_program(data, &ELF_CPU).unwrap();
+    render_crt(Cpu::new(&ELF_CPU, &program))
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    if let Ok(program) = parse_program(&String::from_utf8_lossy(data), &ELF_CPU) {
+        Cpu::new(&ELF_CPU, &program).for_each(drop);
+        render_crt(Cpu::new(&ELF_CPU, &program));
+    }
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: \n{}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || {
+        let screen = solve2(data);
+        aoc::ocr::decode(&screen).unwrap_or_else(|e| format!("{}\n{}", e, screen))
+    });
+    if let Some(mut sink) = aoc::sink::from_args(&CRT_PALETTE, ".#o@", 8, 25.0) {
+        let program = parse_program(data, &ELF_CPU).unwrap();
+        draw_crt(Cpu::new(&ELF_CPU, &program), |screen, state| {
+            sink.push(&crt_frame(screen, state)).unwrap()
+        });
+        sink.finish().unwrap();
+    }
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -136,7 +288,75 @@ mod tests {
 #....#..#..#...#.....#...#..#.#.....#...
 #....#..#.#....#....#....#..#.#..#.#....
 ####.#..#.####.#....####.#..#..##..####.";
-        assert_eq!(solve2(data), expected)
+        assert_eq!(solve2(data), expected);
+        assert_eq!(aoc::ocr::decode(&solve2(data)).unwrap(), "EHZFZHCZ")
+    }
+
+    #[test]
+    fn test_trace_and_hooks() {
+        let program = parse_program("noop\naddx 3\naddx -5", &ELF_CPU).unwrap();
+        let mut seen = vec![];
+        let xs = Cpu::new(&ELF_CPU, &program)
+            .with_hook(4, |s| seen.push(s.registers.x))
+            .map(|s| s.registers.x)
+            .collect::<Vec<_>>();
+        assert_eq!(xs, vec![1, 1, 1, 4, 4]);
+        assert_eq!(seen, vec![4]);
+    }
+
+    #[test]
+    fn test_custom_instruction_set() {
+        let instruction_set = [Instruction {
+            name: "double",
+            arity: 0,
+            cycles: 3,
+            effect: |r, _| r.x *= 2,
+        }];
+        let program = parse_program("double\ndouble", &instruction_set).unwrap();
+        let mut cpu = Cpu::new(&instruction_set, &program).with_breakpoints(&[4]);
+        assert_eq!(
+            cpu.run_to_breakpoint(),
+            Some(CycleState {
+                cycle: 4,
+                registers: Registers { x: 2 }
+            })
+        );
+        assert_eq!(cpu.last().map(|s| s.cycle), Some(6));
+        assert_eq!(
+            parse_program("halt", &ELF_CPU),
+            Err(ProgramError::UnknownInstruction("halt".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_crt_frames() {
+        let program = parse_program(include_str!("../example.txt"), &ELF_CPU).unwrap();
+        let mut frames = vec![];
+        draw_crt(Cpu::new(&ELF_CPU, &program), |screen, state| {
+            frames.push(crt_frame(screen, state))
+        });
+        assert_eq!(frames.len(), 240);
+        // The first pixel is lit in the example and the beam ends bottom right.
+        let last = &frames[239];
+        assert_eq!(last.get(0, 0), 1);
+        assert_eq!(last.get(39, 5), 3);
+        // During the first cycle the sprite covers columns 0 to 2 and the beam is on 0.
+        assert_eq!(&frames[0].pixels[..4], &[3, 2, 2, 0]);
+    }
+
+    #[test]
+    fn test_argument_count() {
+        for line in ["addx", "addx 1 2", "noop 1", "addx x"] {
+            assert_eq!(
+                parse_program(line, &ELF_CPU),
+                Err(ProgramError::BadArgument(line.to_string()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
     }
 
     #[bench]
@@ -4,6 +4,20 @@ use std::cell::RefCell;
 
 extern crate test;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum MonkeyError {
+    MissingLine(&'static str),
+    MalformedLine(String),
+    MalformedOperation(String),
+    /// Monkeys must be numbered from 0 in the order they are listed.
+    UnexpectedId {
+        expected: usize,
+        found: usize,
+    },
+    UnknownTarget(usize),
+    WorryOverflow,
+}
+
 #[derive(Debug)]
 enum Operation {
     Add(i64),
@@ -13,35 +27,34 @@ enum Operation {
 }
 
 impl Operation {
-    fn apply(&self, input: i64) -> i64 {
+    fn apply(&self, input: i64) -> Result<i64, MonkeyError> {
         match self {
-            Operation::Add(n) => input + n,
-            Operation::Multiply(n) => input * n,
-            Operation::Square => input * input,
-            Operation::Double => input + input,
+            Operation::Add(n) => input.checked_add(*n),
+            Operation::Multiply(n) => input.checked_mul(*n),
+            Operation::Square => input.checked_mul(input),
+            Operation::Double => input.checked_add(input),
         }
+        .ok_or(MonkeyError::WorryOverflow)
     }
 }
 
 impl Operation {
-    fn from(s: &str) -> Operation {
-        assert!(s.trim().starts_with("Operation"));
-        let op = s.split_once("old").unwrap().1.trim();
-        let operand = op.split_once(" ").unwrap().1;
-        if op.starts_with("*") {
-            if operand == "old" {
-                return Operation::Square;
-            }
-            let operand_n = operand.parse::<i64>().unwrap();
-            return Operation::Multiply(operand_n);
-        } else if op.starts_with("+") {
-            if operand == "old" {
-                return Operation::Double;
-            }
-            let operand_n = operand.parse::<i64>().unwrap();
-            return Operation::Add(operand_n);
-        } else {
-            panic!("Weird op value {} from {}", op, s);
+    fn from(s: &str) -> Result<Operation, MonkeyError> {
+        let malformed = || MonkeyError::MalformedOperation(s.to_string());
+        let op = s
+            .trim()
+            .strip_prefix("Operation")
+            .and_then(|op| op.split_once("old"))
+            .ok_or_else(malformed)?
+            .1
+            .trim();
+        let (operator, operand) = op.split_once(" ").ok_or_else(malformed)?;
+        match (operator, operand) {
+            ("*", "old") => Ok(Operation::Square),
+            ("+", "old") => Ok(Operation::Double),
+            ("*", n) => Ok(Operation::Multiply(n.parse().map_err(|_| malformed())?)),
+            ("+", n) => Ok(Operation::Add(n.parse().map_err(|_| malformed())?)),
+            _ => Err(malformed()),
         }
     }
 }
@@ -53,15 +66,76 @@ struct Monkeys {
 }
 
 impl Monkeys {
-    fn from(monkeys: Vec<Monkey>) -> Monkeys {
-        Monkeys {
-            count: monkeys.len(),
-            monkeys: monkeys,
+    fn from(monkeys: Vec<Monkey>) -> Result<Monkeys, MonkeyError> {
+        for (expected, monkey) in monkeys.iter().enumerate() {
+            if monkey.id != expected {
+                return Err(MonkeyError::UnexpectedId {
+                    expected,
+                    found: monkey.id,
+                });
+            }
+            if let Some(target) = [monkey.test_true, monkey.test_false]
+                .into_iter()
+                .find(|t| *t >= monkeys.len())
+            {
+                return Err(MonkeyError::UnknownTarget(target));
+            }
         }
+        Ok(Monkeys {
+            count: monkeys.len(),
+            monkeys,
+        })
     }
-    fn push_item_to(&mut self, i: usize, item: i64) {
+
+    fn push_item_to(&self, i: usize, item: i64) {
         self.monkeys[i].items.borrow_mut().push(item);
     }
+
+    /// Plays `rounds` rounds, calling `relieve` on every worry level after the
+    /// operation, and returns how many items each monkey inspected.
+    fn play(&self, rounds: usize, relieve: impl Fn(i64) -> i64) -> Result<Vec<usize>, MonkeyError> {
+        let mut inspections = vec![0usize; self.count];
+        for _ in 0..rounds {
+            for (i, monkey) in self.monkeys.iter().enumerate() {
+                // Taken out first, as a monkey may throw to itself.
+                let items = std::mem::take(&mut *monkey.items.borrow_mut());
+                inspections[i] += items.len();
+                for item in items {
+                    let worry_level = relieve(monkey.operation.apply(item)?);
+                    let throw_to = if worry_level % monkey.test == 0 {
+                        monkey.test_true
+                    } else {
+                        monkey.test_false
+                    };
+                    self.push_item_to(throw_to, worry_level);
+                }
+            }
+        }
+        Ok(inspections)
+    }
+
+    /// A worry level's remainder modulo every monkey's test divisor at once.
+    fn common_multiple(&self) -> Result<i64, MonkeyError> {
+        self.monkeys.iter().try_fold(1i64, |m, monkey| {
+            (m / gcd(m, monkey.test))
+                .checked_mul(monkey.test)
+                .ok_or(MonkeyError::WorryOverflow)
+        })
+    }
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// The product of the two highest inspection counts.
+fn monkey_business(mut inspections: Vec<usize>) -> usize {
+    inspections.sort_unstable_by(|a, b| b.cmp(a));
+    inspections.iter().take(2).product()
 }
 
 #[derive(Debug)]
@@ -74,110 +148,93 @@ struct Monkey {
     test_false: usize,
 }
 
+/// The trimmed text after `prefix` in `line`.
+fn field<'a>(line: &'a str, prefix: &str) -> Result<&'a str, MonkeyError> {
+    line.split_once(prefix)
+        .map(|(_, rest)| rest.trim())
+        .ok_or_else(|| MonkeyError::MalformedLine(line.to_string()))
+}
+
 impl Monkey {
-    fn from(s: &str) -> Monkey {
+    fn from(s: &str) -> Result<Monkey, MonkeyError> {
         let mut lines = s.lines();
-        let monkey_id = lines
-            .next()
-            .unwrap()
-            .split_once(" ")
-            .unwrap()
-            .1
+        let mut next = |name| lines.next().ok_or(MonkeyError::MissingLine(name));
+        let malformed = |line: &str| MonkeyError::MalformedLine(line.to_string());
+        let line = next("id")?;
+        let monkey_id = field(line, " ")?
             .trim_end_matches(":")
             .parse::<usize>()
-            .unwrap();
-        let starting_items = lines
-            .next()
-            .unwrap()
-            .split_once(": ")
-            .unwrap()
-            .1
-            .split(", ")
-            .map(|e| e.parse::<i64>().unwrap())
-            .collect::<Vec<i64>>();
-        let operation = Operation::from(lines.next().unwrap());
-        let test = lines
-            .next()
-            .unwrap()
-            .split_once("by ")
-            .unwrap()
-            .1
+            .map_err(|_| malformed(line))?;
+        let line = next("starting items")?;
+        let starting_items = field(line, ":")?
+            .split(",")
+            .map(|e| e.trim().parse::<i64>())
+            .collect::<Result<Vec<i64>, _>>()
+            .map_err(|_| malformed(line))?;
+        let operation = Operation::from(next("operation")?)?;
+        let line = next("test")?;
+        let test = field(line, "by ")?
             .parse::<i64>()
-            .unwrap();
-        let test_true = lines
-            .next()
-            .unwrap()
-            .split_once("monkey ")
-            .unwrap()
-            .1
+            .ok()
+            .filter(|n| *n > 0)
+            .ok_or_else(|| malformed(line))?;
+        let line = next("true target")?;
+        let test_true = field(line, "monkey ")?
             .parse::<usize>()
-            .unwrap();
-        let test_false = lines
-            .next()
-            .unwrap()
-            .split_once("monkey ")
-            .unwrap()
-            .1
+            .map_err(|_| malformed(line))?;
+        let line = next("false target")?;
+        let test_false = field(line, "monkey ")?
             .parse::<usize>()
-            .unwrap();
-        Monkey {
+            .map_err(|_| malformed(line))?;
+        Ok(Monkey {
             id: monkey_id,
             items: RefCell::new(starting_items),
-            operation: operation,
-            test: test,
-            test_true: test_true,
-            test_false: test_false,
-        }
-    }
-
-    fn push_item(&mut self, item: i64) {
-        self.items.borrow_mut().push(item);
+            operation,
+            test,
+            test_true,
+            test_false,
+        })
     }
 }
 
-fn parse(data: &str) -> Monkeys {
+fn parse(data: &str) -> Result<Monkeys, MonkeyError> {
     let monkeys = data
         .split("\n\n")
         .map(Monkey::from)
-        .collect::<Vec<Monkey>>();
+        .collect::<Result<Vec<Monkey>, _>>()?;
     Monkeys::from(monkeys)
 }
 
 fn solve1(data: &str) -> usize {
-    let mut monkeys: Monkeys = parse(data);
-    let num_monkeys = monkeys.count;
-    let mut monkeyInspections = vec![0usize; num_monkeys];
-    println!("{:?}", monkeys);
-    for _ in 0..1 {
-        for i in 0..num_monkeys {
-            let mut monkey = monkeys[i];
-            let getPrevInspections = monkeyInspections.get_mut(i).unwrap();
-            monkeyInspections[i] = *getPrevInspections + monkey.items.borrow().len();
-            for item in monkey.items.borrow().iter() {
-                let mut worry_level = item.clone();
-                worry_level = monkey.operation.apply(worry_level);
-                let throw_to = if worry_level % monkey.test == 0 {
-                    monkey.test_true
-                } else {
-                    monkey.test_false
-                };
-                monkeys.push_item_to(i, worry_level);
-            }
-        }
-    }
-    0
+    let monkeys = parse(data).unwrap();
+    monkey_business(monkeys.play(20, |w| w / 3).unwrap())
 }
 
+/// Without relief worry levels grow without bound, so they are kept modulo a common
+/// multiple of the test divisors, which leaves every test's result unchanged.
 fn solve2(data: &str) -> usize {
-    0
+    let monkeys = parse(data).unwrap();
+    let modulus = monkeys.common_multiple().unwrap();
+    monkey_business(monkeys.play(10000, |w| w % modulus).unwrap())
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    if let Ok(monkeys) = parse(&String::from_utf8_lossy(data)) {
+        let _ = monkeys.play(20, |w| w / 3);
+    }
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
+#[cfg(test)]
 mod tests {
     use super::*;
     use test::Bencher;
@@ -191,19 +248,67 @@ mod tests {
     #[test]
     fn test_part1_regression() {
         let data = include_str!("../input.txt");
-        assert_eq!(solve1(data), 6236)
+        assert_eq!(solve1(data), 50172)
     }
 
     #[test]
     fn test_part2() {
         let data1 = include_str!("../example.txt");
-        assert_eq!(solve2(data1), 1);
+        assert_eq!(solve2(data1), 2713310158);
     }
 
     #[test]
     fn test_part2_regression() {
         let data = include_str!("../input.txt");
-        assert_eq!(solve2(data), 2449)
+        assert_eq!(solve2(data), 11614682178)
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert!(matches!(
+            Operation::from("  Operation: new = old * old"),
+            Ok(Operation::Square)
+        ));
+        assert_eq!(
+            Operation::from("  Operation: new = old / 2").unwrap_err(),
+            MonkeyError::MalformedOperation("  Operation: new = old / 2".to_string())
+        );
+        assert_eq!(
+            parse("Monkey 0:\n  Starting items: 79").unwrap_err(),
+            MonkeyError::MissingLine("operation")
+        );
+        let example = include_str!("../example.txt");
+        assert_eq!(
+            parse(&example.replace("Monkey 1:", "Monkey 5:")).unwrap_err(),
+            MonkeyError::UnexpectedId {
+                expected: 1,
+                found: 5
+            }
+        );
+        assert_eq!(
+            parse(&example.replace("throw to monkey 3", "throw to monkey 4")).unwrap_err(),
+            MonkeyError::UnknownTarget(4)
+        );
+        assert_eq!(
+            parse(&example.replace("divisible by 23", "divisible by -23")).unwrap_err(),
+            MonkeyError::MalformedLine("  Test: divisible by -23".to_string())
+        );
+    }
+
+    #[test]
+    fn test_monkey_throwing_to_itself() {
+        let monkeys = parse(
+            "Monkey 0:\n  Starting items: 1, 2\n  Operation: new = old + 1\n  \
+             Test: divisible by 2\n    If true: throw to monkey 0\n    If false: throw to monkey 0",
+        )
+        .unwrap();
+        assert_eq!(monkeys.play(3, |w| w), Ok(vec![6]));
+        assert_eq!(*monkeys.monkeys[0].items.borrow(), vec![4, 5]);
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
     }
 
     #[bench]
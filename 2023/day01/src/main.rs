@@ -2,104 +2,205 @@
 
 extern crate test;
 
+use std::collections::VecDeque;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CalibrationError {
+    NoDigits(String),
+}
+
+const ASCII_DIGITS: [(&str, u32); 10] = [
+    ("0", 0),
+    ("1", 1),
+    ("2", 2),
+    ("3", 3),
+    ("4", 4),
+    ("5", 5),
+    ("6", 6),
+    ("7", 7),
+    ("8", 8),
+    ("9", 9),
+];
+
+const ENGLISH_DIGIT_WORDS: [(&str, u32); 9] = [
+    ("one", 1),
+    ("two", 2),
+    ("three", 3),
+    ("four", 4),
+    ("five", 5),
+    ("six", 6),
+    ("seven", 7),
+    ("eight", 8),
+    ("nine", 9),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct DigitMatch {
+    start: usize,
+    end: usize,
+    value: u32,
+}
+
+/// Aho-Corasick automaton over bytes. Every state has a full transition table and
+/// carries the outputs of its whole suffix-link chain, so overlapping words such as
+/// `twone` are all reported while scanning each byte exactly once.
+struct DigitScanner {
+    transitions: Vec<[usize; 256]>,
+    outputs: Vec<Vec<(usize, u32)>>,
+}
+
+impl DigitScanner {
+    fn new(vocabulary: &[(&str, u32)]) -> Self {
+        let mut transitions: Vec<[usize; 256]> = vec![[0; 256]];
+        let mut outputs: Vec<Vec<(usize, u32)>> = vec![vec![]];
+        let mut is_edge: Vec<[bool; 256]> = vec![[false; 256]];
+        for (word, value) in vocabulary.iter().filter(|(w, _)| !w.is_empty()) {
+            let mut state = 0;
+            for b in word.bytes() {
+                if !is_edge[state][b as usize] {
+                    transitions.push([0; 256]);
+                    outputs.push(vec![]);
+                    is_edge.push([false; 256]);
+                    transitions[state][b as usize] = transitions.len() - 1;
+                    is_edge[state][b as usize] = true;
+                }
+                state = transitions[state][b as usize];
+            }
+            outputs[state].push((word.len(), *value));
+        }
+
+        let mut fail = vec![0; transitions.len()];
+        let mut queue = VecDeque::new();
+        for b in 0..256 {
+            if is_edge[0][b] {
+                queue.push_back(transitions[0][b]);
+            }
+        }
+        while let Some(state) = queue.pop_front() {
+            let inherited = outputs[fail[state]].clone();
+            outputs[state].extend(inherited);
+            for b in 0..256 {
+                let fallback = transitions[fail[state]][b];
+                if is_edge[state][b] {
+                    let next = transitions[state][b];
+                    fail[next] = fallback;
+                    queue.push_back(next);
+                } else {
+                    transitions[state][b] = fallback;
+                }
+            }
+        }
+        DigitScanner {
+            transitions,
+            outputs,
+        }
+    }
+
+    fn digits() -> Self {
+        DigitScanner::new(&ASCII_DIGITS)
+    }
+
+    fn digits_and_english_words() -> Self {
+        DigitScanner::new(&[&ASCII_DIGITS[..], &ENGLISH_DIGIT_WORDS[..]].concat())
+    }
+
+    fn matches<'a>(&'a self, haystack: &'a [u8]) -> Matches<'a> {
+        Matches {
+            scanner: self,
+            haystack,
+            pos: 0,
+            state: 0,
+            output: 0,
+        }
+    }
+
+    fn first_and_last(&self, haystack: &[u8]) -> Option<(DigitMatch, DigitMatch)> {
+        self.matches(haystack).fold(None, |acc, m| match acc {
+            None => Some((m, m)),
+            Some((first, last)) => Some((
+                if m.start < first.start { m } else { first },
+                if m.start > last.start { m } else { last },
+            )),
+        })
+    }
+
+    fn calibration_value(&self, line: &str) -> Result<u32, CalibrationError> {
+        let (first, last) = self
+            .first_and_last(line.as_bytes())
+            .ok_or_else(|| CalibrationError::NoDigits(line.to_string()))?;
+        Ok(first.value * 10 + last.value)
+    }
+}
+
+struct Matches<'a> {
+    scanner: &'a DigitScanner,
+    haystack: &'a [u8],
+    pos: usize,
+    state: usize,
+    output: usize,
+}
+
+impl Iterator for Matches<'_> {
+    type Item = DigitMatch;
+
+    fn next(&mut self) -> Option<DigitMatch> {
+        loop {
+            if let Some((len, value)) = self.scanner.outputs[self.state].get(self.output) {
+                self.output += 1;
+                return Some(DigitMatch {
+                    start: self.pos - len,
+                    end: self.pos,
+                    value: *value,
+                });
+            }
+            let b = *self.haystack.get(self.pos)?;
+            self.state = self.scanner.transitions[self.state][b as usize];
+            self.pos += 1;
+            self.output = 0;
+        }
+    }
+}
+
 fn parse(data: &str) -> Vec<&str> {
-    data.split("\n").collect()
+    data.split("\n").filter(|l| !l.is_empty()).collect()
 }
 
 fn solve1(data: &str) -> u32 {
-    let lines = parse(data);
-    lines
+    let scanner = DigitScanner::digits();
+    parse(data)
         .iter()
-        .map(|l| l.chars().filter(|c| c.is_numeric()).collect::<String>())
-        .map(|l| format!("{}{}", l.chars().next().unwrap(), l.chars().last().unwrap()))
-        .map(|l| l.parse::<u32>().unwrap())
+        .map(|l| scanner.calibration_value(l).unwrap())
         .sum()
 }
 
 fn solve2(data: &str) -> u32 {
-    let digit_strings_to_nums: Vec<(&str, &str)> = vec![
-        ("one", "1"),
-        ("two", "2"),
-        ("three", "3"),
-        ("four", "4"),
-        ("five", "5"),
-        ("six", "6"),
-        ("seven", "7"),
-        ("eight", "8"),
-        ("nine", "9"),
-    ];
-    let digit_strings_rev_to_nums: Vec<(String, String)> = digit_strings_to_nums
-        .iter()
-        .map(|(s, n)| (s.chars().rev().collect::<String>(), String::from(n.clone())))
-        .collect();
-    let digit_strings: Vec<String> = digit_strings_to_nums
-        .iter()
-        .map(|(s, _)| String::from(s.clone()))
-        .collect();
-    let digit_strings_rev: Vec<String> = digit_strings_rev_to_nums
-        .iter()
-        .map(|(s, _)| String::from(s))
-        .collect();
-    let lines = parse(data);
-
-    let first_and_last_digits: Vec<String> = lines
-        .iter()
-        .map(|l| {
-            let first_digit_start = (0..l.len())
-                .map(|n| l.chars().skip(n).collect::<String>())
-                .find(|c| {
-                    c.chars().next().unwrap().is_numeric()
-                        || digit_strings
-                            .iter()
-                            .find(|s| c.starts_with(s.clone()))
-                            .is_some()
-                })
-                .unwrap();
-            let first_digit = if first_digit_start.chars().next().unwrap().is_numeric() {
-                first_digit_start.chars().take(1).collect::<String>()
-            } else {
-                digit_strings_to_nums
-                    .iter()
-                    .find(|(s, _)| first_digit_start.starts_with(s))
-                    .map(|(_, n)| n)
-                    .unwrap()
-                    .to_string()
-            };
-
-            let last_digit_start = (0..l.len())
-                .map(|n| l.chars().rev().skip(n).collect::<String>())
-                .find(|c| {
-                    c.chars().next().unwrap().is_numeric()
-                        || digit_strings_rev
-                            .iter()
-                            .find(|s| c.starts_with(s.clone()))
-                            .is_some()
-                })
-                .unwrap();
-            let last_digit = if last_digit_start.chars().next().unwrap().is_numeric() {
-                last_digit_start.chars().take(1).collect::<String>()
-            } else {
-                digit_strings_rev_to_nums
-                    .iter()
-                    .find(|(s, _)| last_digit_start.starts_with(s))
-                    .map(|(_, n)| n)
-                    .unwrap()
-                    .to_string()
-            };
-            format!("{}{}", first_digit, last_digit)
-        })
-        .collect();
-
-    first_and_last_digits
+    let scanner = DigitScanner::digits_and_english_words();
+    parse(data)
         .iter()
-        .map(|n| n.parse::<u32>().unwrap())
+        .map(|l| scanner.calibration_value(l).unwrap())
         .sum()
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let data = String::from_utf8_lossy(data);
+    for scanner in [
+        DigitScanner::digits(),
+        DigitScanner::digits_and_english_words(),
+    ] {
+        for line in parse(&data) {
+            let _ = scanner.calibration_value(line);
+        }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -119,6 +220,68 @@ mod tests {
         assert_eq!(solve2(data), 281)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 54450)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 54265)
+    }
+
+    #[test]
+    fn test_overlapping_words() {
+        let scanner = DigitScanner::digits_and_english_words();
+        assert_eq!(scanner.calibration_value("twone"), Ok(21));
+        assert_eq!(scanner.calibration_value("eightwo"), Ok(82));
+        let values = scanner
+            .matches(b"xeightwone")
+            .map(|m| (m.start, m.value))
+            .collect::<Vec<_>>();
+        assert_eq!(values, vec![(1, 8), (5, 2), (7, 1)]);
+    }
+
+    #[test]
+    fn test_custom_vocabulary() {
+        let scanner = DigitScanner::new(&[("zero", 0), ("eins", 1), ("zwei", 2), ("drei", 3)]);
+        let (first, last) = scanner.first_and_last(b"xzweinszerodreix").unwrap();
+        assert_eq!(
+            first,
+            DigitMatch {
+                start: 1,
+                end: 5,
+                value: 2
+            }
+        );
+        assert_eq!(
+            last,
+            DigitMatch {
+                start: 11,
+                end: 15,
+                value: 3
+            }
+        );
+        assert_eq!(scanner.first_and_last(b"nothing"), None);
+        assert_eq!(
+            scanner.calibration_value("nothing"),
+            Err(CalibrationError::NoDigits("nothing".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(
+            fuzz_parse,
+            &[
+                include_bytes!("../example.txt"),
+                include_bytes!("../example2.txt"),
+            ],
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
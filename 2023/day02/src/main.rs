@@ -2,125 +2,221 @@
 
 extern crate test;
 
+use std::collections::BTreeMap;
+
 fn parse(data: &str) -> Vec<&str> {
-    data.split("\n").collect()
+    data.split("\n").filter(|l| !l.is_empty()).collect()
 }
 
-#[derive(Debug, Clone, Copy)]
-struct CubeSet {
-    red: u16,
-    green: u16,
-    blue: u16,
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ParseError {
+    MissingId(String),
+    MalformedCubes(String),
 }
 
-impl CubeSet {
-    fn power(&self) -> u32 {
-        self.red as u32 * self.green as u32 * self.blue as u32
+/// Cube counts keyed by colour. Colours that are not present count as zero.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+struct CubeSet<'a>(BTreeMap<&'a str, u32>);
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Violation<'a> {
+    draw: usize,
+    colour: &'a str,
+    drawn: u32,
+    available: u32,
+}
+
+impl<'a> CubeSet<'a> {
+    // 3 blue, 4 red
+    fn parse(s: &'a str) -> Result<CubeSet<'a>, ParseError> {
+        let mut set = CubeSet::default();
+        for cubes in s.split(",").map(|c| c.trim()).filter(|c| !c.is_empty()) {
+            let (n, colour) = cubes
+                .split_once(" ")
+                .and_then(|(n, colour)| Some((n.parse::<u32>().ok()?, colour.trim())))
+                .ok_or_else(|| ParseError::MalformedCubes(cubes.to_string()))?;
+            let count = set.0.entry(colour).or_default();
+            *count = count
+                .checked_add(n)
+                .ok_or_else(|| ParseError::MalformedCubes(cubes.to_string()))?;
+        }
+        Ok(set)
+    }
+
+    fn count(&self, colour: &str) -> u32 {
+        self.0.get(colour).copied().unwrap_or(0)
+    }
+
+    #[cfg(test)]
+    fn total(&self) -> u32 {
+        self.0.values().sum()
+    }
+
+    /// `None` when the product does not fit a `u64`.
+    fn power(&self) -> Option<u64> {
+        self.0
+            .values()
+            .try_fold(1u64, |power, n| power.checked_mul(*n as u64))
     }
 
-    fn fits_within_constraint_set(&self, constraint_set: &CubeSet) -> bool {
-        self.red <= constraint_set.red
-            && self.green <= constraint_set.green
-            && self.blue <= constraint_set.blue
+    fn union_max(&self, other: &CubeSet<'a>) -> CubeSet<'a> {
+        let mut set = self.clone();
+        for (colour, n) in &other.0 {
+            let curr = set.0.entry(colour).or_default();
+            *curr = Ord::max(*curr, *n);
+        }
+        set
     }
 }
 
 #[derive(Debug, Clone)]
-struct Game {
+struct Game<'a> {
     id: u32,
-    sets: Vec<CubeSet>,
+    sets: Vec<CubeSet<'a>>,
 }
 
-impl Game {
-    fn is_possible_with_set(&self, set: &CubeSet) -> bool {
+#[cfg(test)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GameStats<'a> {
+    min_bag: CubeSet<'a>,
+    largest_draw: u32,
+    draws: usize,
+}
+
+impl<'a> Game<'a> {
+    fn min_possible_set(&self) -> CubeSet<'a> {
         self.sets
             .iter()
-            .all(|gs| gs.fits_within_constraint_set(set))
-    }
-    fn min_possible_set(&self) -> CubeSet {
-        self.sets.iter().fold(
-            CubeSet {
-                red: 0,
-                green: 0,
-                blue: 0,
-            },
-            |acc, cs| CubeSet {
-                red: Ord::max(acc.red, cs.red),
-                green: Ord::max(acc.green, cs.green),
-                blue: Ord::max(acc.blue, cs.blue),
-            },
-        )
+            .fold(CubeSet::default(), |acc, cs| acc.union_max(cs))
+    }
+
+    /// Every draw and colour that needs more cubes than `bag` holds; empty when the
+    /// game is possible.
+    fn violations(&self, bag: &CubeSet) -> Vec<Violation<'a>> {
+        self.sets
+            .iter()
+            .enumerate()
+            .flat_map(|(draw, set)| {
+                set.0.iter().filter_map(move |(colour, n)| {
+                    (*n > bag.count(colour)).then_some(Violation {
+                        draw,
+                        colour,
+                        drawn: *n,
+                        available: bag.count(colour),
+                    })
+                })
+            })
+            .collect()
+    }
+
+    #[cfg(test)]
+    fn stats(&self) -> GameStats<'a> {
+        GameStats {
+            min_bag: self.min_possible_set(),
+            largest_draw: self.sets.iter().map(|s| s.total()).max().unwrap_or(0),
+            draws: self.sets.len(),
+        }
     }
 }
 
 // Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
-fn parse_game(line: &str) -> Game {
-    let id = line
-        .split(":")
-        .next()
-        .unwrap()
-        .split(" ")
-        .last()
-        .unwrap()
-        .parse::<u32>()
-        .unwrap();
-    let sets = line
-        .split(":")
-        .last()
-        .unwrap()
+fn parse_game(line: &str) -> Result<Game<'_>, ParseError> {
+    let (header, sets) = line
+        .split_once(":")
+        .ok_or_else(|| ParseError::MissingId(line.to_string()))?;
+    let id = header
+        .strip_prefix("Game ")
+        .and_then(|id| id.trim().parse::<u32>().ok())
+        .ok_or_else(|| ParseError::MissingId(line.to_string()))?;
+    let sets = sets
         .split(";")
-        .map(|set| {
-            set.split(",").fold(
-                CubeSet {
-                    red: 0,
-                    green: 0,
-                    blue: 0,
-                },
-                |acc, s| {
-                    let ss: Vec<&str> = s.trim().split(" ").collect();
-                    let n = ss.get(0).unwrap().parse::<u16>().unwrap();
-                    match ss.get(1).unwrap().as_ref() {
-                        "red" => CubeSet { red: n, ..acc },
-                        "green" => CubeSet { green: n, ..acc },
-                        "blue" => CubeSet { blue: n, ..acc },
-                        _ => panic!("Y here w/ no rgb? {}", s),
-                    }
-                },
-            )
-        })
-        .collect();
-    Game { id, sets }
+        .map(CubeSet::parse)
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(Game { id, sets })
 }
 
-fn solve1(data: &str) -> u32 {
-    let test_set = CubeSet {
-        red: 12,
-        green: 13,
-        blue: 14,
-    };
-    let lines = parse(data);
-    lines
+/// Ids of the games `bag` cannot produce, with the constraints each one breaks.
+fn impossible_games<'a>(games: &[Game<'a>], bag: &CubeSet) -> Vec<(u32, Vec<Violation<'a>>)> {
+    games
         .iter()
-        .map(|l| parse_game(l))
-        .filter(|g| g.is_possible_with_set(&test_set))
+        .map(|g| (g.id, g.violations(bag)))
+        .filter(|(_, vs)| !vs.is_empty())
+        .collect()
+}
+
+/// The bag part 1 asks about unless `--bag` names another one.
+const PUZZLE_BAG: &str = "12 red, 13 green, 14 blue";
+
+/// The value of `--bag "<cubes>"` or `--bag=<cubes>`, if given.
+fn bag_arg(args: impl IntoIterator<Item = String>) -> Option<String> {
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--bag" {
+            return args.next();
+        } else if let Some(bag) = arg.strip_prefix("--bag=") {
+            return Some(bag.to_string());
+        }
+    }
+    None
+}
+
+fn solve1(data: &str, bag: &str) -> u32 {
+    let test_set = CubeSet::parse(bag).unwrap();
+    let games = parse(data)
+        .iter()
+        .map(|l| parse_game(l).unwrap())
+        .collect::<Vec<_>>();
+    let impossible = impossible_games(&games, &test_set);
+    games
+        .iter()
+        .filter(|g| impossible.iter().all(|(id, _)| *id != g.id))
         .map(|g| g.id)
         .sum()
 }
 
-fn solve2(data: &str) -> u32 {
+fn solve2(data: &str) -> u64 {
     let lines = parse(data);
-    lines
+    let games = lines
         .iter()
-        .map(|l| parse_game(l))
-        .map(|g| g.min_possible_set())
-        .map(|s| s.power())
-        .sum()
+        .map(|l| parse_game(l).unwrap())
+        .collect::<Vec<_>>();
+    // A colour seen anywhere but never drawn in a game still needs zero cubes of it,
+    // which makes that game's power zero.
+    let colours = games
+        .iter()
+        .flat_map(|g| g.sets.iter().flat_map(|s| s.0.keys().copied()))
+        .map(|c| (c, 0))
+        .collect::<BTreeMap<_, _>>();
+    games
+        .iter()
+        .map(|g| CubeSet(colours.clone()).union_max(&g.min_possible_set()))
+        .try_fold(0u64, |total, s| total.checked_add(s.power()?))
+        .expect("total power does not fit a u64")
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    for game in parse(&String::from_utf8_lossy(data))
+        .iter()
+        .filter_map(|l| parse_game(l).ok())
+    {
+        game.min_possible_set().power();
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    let bag = bag_arg(std::env::args().skip(1)).unwrap_or_else(|| PUZZLE_BAG.to_string());
+    if let Err(e) = CubeSet::parse(&bag) {
+        eprintln!("invalid --bag {:?}: {:?}", bag, e);
+        eprintln!("usage: day02 [--bag \"12 red, 13 green, 14 blue\"]");
+        std::process::exit(2);
+    }
+    aoc::report::part(1, || solve1(data, &bag));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -131,7 +227,7 @@ mod tests {
     #[test]
     fn test_part1() {
         let data = include_str!("../example.txt");
-        assert_eq!(solve1(data), 8)
+        assert_eq!(solve1(data, PUZZLE_BAG), 8)
     }
 
     #[test]
@@ -140,10 +236,117 @@ mod tests {
         assert_eq!(solve2(data), 2286)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data, PUZZLE_BAG), 2149)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 71274)
+    }
+
+    #[test]
+    fn test_arbitrary_colours() {
+        let game = parse_game("Game 7: 3 teal, 2 red; 5 teal, 1 ochre").unwrap();
+        let stats = game.stats();
+        assert_eq!(
+            stats.min_bag,
+            CubeSet::parse("5 teal, 2 red, 1 ochre").unwrap()
+        );
+        assert_eq!(stats.min_bag.power(), Some(10));
+        assert_eq!(stats.largest_draw, 6);
+        assert_eq!(stats.draws, 2);
+        assert!(game.violations(&stats.min_bag).is_empty());
+    }
+
+    #[test]
+    fn test_impossible_games_report() {
+        let data = include_str!("../example.txt");
+        let games = parse(data)
+            .iter()
+            .map(|l| parse_game(l).unwrap())
+            .collect::<Vec<_>>();
+        let bag = CubeSet::parse(PUZZLE_BAG).unwrap();
+        let report = impossible_games(&games, &bag);
+        assert_eq!(
+            report.iter().map(|(id, _)| *id).collect::<Vec<_>>(),
+            vec![3, 4]
+        );
+        assert_eq!(
+            report[1].1,
+            vec![
+                Violation {
+                    draw: 2,
+                    colour: "blue",
+                    drawn: 15,
+                    available: 14
+                },
+                Violation {
+                    draw: 2,
+                    colour: "red",
+                    drawn: 14,
+                    available: 12
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_game("Game x: 1 red").unwrap_err(),
+            ParseError::MissingId("Game x: 1 red".to_string())
+        );
+        assert_eq!(
+            parse_game("Game 1: red").unwrap_err(),
+            ParseError::MalformedCubes("red".to_string())
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(
+            fuzz_parse,
+            &[
+                include_bytes!("../example.txt"),
+                include_bytes!("../../../fuzz/corpus/y2023_day02/cube-overflow.txt"),
+            ],
+        );
+    }
+
+    #[test]
+    fn test_bag_arg() {
+        let args = |a: &[&str]| a.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        assert_eq!(bag_arg(args(&["--stats"])), None);
+        assert_eq!(
+            bag_arg(args(&["--bag", "1 red, 2 blue"])),
+            Some("1 red, 2 blue".to_string())
+        );
+        assert_eq!(
+            bag_arg(args(&["--stats", "--bag=3 green"])),
+            Some("3 green".to_string())
+        );
+        let data = include_str!("../example.txt");
+        assert_eq!(solve1(data, "20 red, 20 green, 20 blue"), 15);
+    }
+
+    #[test]
+    fn test_cube_overflow() {
+        assert_eq!(
+            parse_game("Game 1: 4294967295 red, 1 red").unwrap_err(),
+            ParseError::MalformedCubes("1 red".to_string())
+        );
+        let set = CubeSet::parse("4294967295 red, 4294967295 green, 4294967295 blue").unwrap();
+        assert_eq!(set.power(), None);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
-        b.iter(|| solve1(data));
+        b.iter(|| solve1(data, PUZZLE_BAG));
     }
 
     #[bench]
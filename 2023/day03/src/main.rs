@@ -1,159 +1,242 @@
 #![feature(test)]
 
-use std::collections::HashSet;
-
 extern crate test;
 
-#[derive(PartialEq, Hash, Eq, PartialOrd, Ord, Clone, Copy, Debug)]
-struct Coord {
-    x: usize,
-    y: usize,
+use std::fmt::{self, Display};
+use std::iter::{Product, Sum};
+use std::ops::{Add, Mul};
+
+const LIMB: u64 = 1_000_000_000;
+
+/// A non-negative integer of any size, in base 10⁹ limbs with the least significant
+/// first and no zero limbs on top, so zero has none.
+#[derive(PartialEq, Eq, Clone, Default, Debug)]
+struct Decimal(Vec<u64>);
+
+impl Decimal {
+    fn parse(digits: &str) -> Decimal {
+        let mut limbs = digits
+            .as_bytes()
+            .rchunks(9)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .fold(0, |acc, d| acc * 10 + u64::from(d - b'0'))
+            })
+            .collect::<Vec<_>>();
+        while limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        Decimal(limbs)
+    }
 }
 
-#[derive(Hash, PartialEq, Eq, Clone, Copy, Debug)]
-struct Item {
-    coord: Coord,
-    char: char,
+impl Add for Decimal {
+    type Output = Decimal;
+
+    fn add(self, other: Decimal) -> Decimal {
+        let mut limbs = vec![];
+        let mut carry = 0;
+        for i in 0..self.0.len().max(other.0.len()) {
+            let sum = self.0.get(i).unwrap_or(&0) + other.0.get(i).unwrap_or(&0) + carry;
+            limbs.push(sum % LIMB);
+            carry = sum / LIMB;
+        }
+        if carry > 0 {
+            limbs.push(carry);
+        }
+        Decimal(limbs)
+    }
 }
 
-#[derive(Hash, PartialEq, PartialOrd, Ord, Eq, Clone, Debug)]
-struct Number {
-    n: u16,
-    coords: Vec<Coord>,
+impl Mul for Decimal {
+    type Output = Decimal;
+
+    fn mul(self, other: Decimal) -> Decimal {
+        if self.0.is_empty() || other.0.is_empty() {
+            return Decimal::default();
+        }
+        let mut limbs = vec![0; self.0.len() + other.0.len()];
+        for (i, a) in self.0.iter().enumerate() {
+            let mut carry = 0;
+            for (j, b) in other.0.iter().enumerate() {
+                let product = limbs[i + j] + a * b + carry;
+                limbs[i + j] = product % LIMB;
+                carry = product / LIMB;
+            }
+            limbs[i + other.0.len()] = carry;
+        }
+        if limbs.last() == Some(&0) {
+            limbs.pop();
+        }
+        Decimal(limbs)
+    }
 }
 
-fn parse(data: &str) -> Vec<&str> {
-    data.split("\n").collect()
+impl Sum for Decimal {
+    fn sum<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal::default(), Add::add)
+    }
 }
 
-const ADJACENT_COORD_OFFSETS: [(i16, i16); 8] = [
-    (1, 1),
-    (1, 0),
-    (1, -1),
-    (0, 1),
-    (0, -1),
-    (-1, 1),
-    (-1, 0),
-    (-1, -1),
-];
+impl Product for Decimal {
+    fn product<I: Iterator<Item = Decimal>>(iter: I) -> Decimal {
+        iter.fold(Decimal(vec![1]), Mul::mul)
+    }
+}
 
-fn coord_is_adjacent_to_symbol(symbol_coords: &HashSet<Coord>, coord: &Coord) -> bool {
-    ADJACENT_COORD_OFFSETS
-        .iter()
-        .filter(|(x, y)| !((coord.x == 0 && *x < 0) || (coord.y == 0 && *y < 0)))
-        .map(|(x_offset, y_offset)| Coord {
-            x: (coord.x as i16 + x_offset) as usize,
-            y: (coord.y as i16 + y_offset) as usize,
-        })
-        .any(|c| symbol_coords.contains(&c))
-}
-
-fn items_to_nums(items: &Vec<Item>) -> Vec<Number> {
-    let mut curr_num_str: String = String::from("");
-    let mut curr_num_coords: Vec<Coord> = vec![];
-    let mut nums: Vec<Number> = vec![];
-    for item in items.iter() {
-        if item.char.is_numeric() {
-            curr_num_str.push(item.char);
-            curr_num_coords.push(item.coord);
-            continue;
-        } else if curr_num_str.len() > 0 {
-            nums.push(Number {
-                n: curr_num_str.parse::<u16>().unwrap(),
-                coords: curr_num_coords.clone(),
-            });
-            curr_num_str.clear();
-            curr_num_coords.clear();
-        }
+impl Display for Decimal {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Some((top, rest)) = self.0.split_last() else {
+            return write!(f, "0");
+        };
+        write!(f, "{}", top)?;
+        rest.iter()
+            .rev()
+            .try_for_each(|limb| write!(f, "{:09}", limb))
     }
-    nums
 }
 
-fn solve1(data: &str) -> u32 {
-    let items: Vec<Item> = parse(data)
-        .iter()
-        .enumerate()
-        .flat_map(|(y, l)| {
-            l.chars().enumerate().map(move |(x, c)| Item {
-                coord: Coord { y, x },
-                char: c,
-            })
-        })
-        .collect();
-    let symbol_coords = items
-        .iter()
-        .filter(|item| item.char != '.' && item.char.is_ascii_punctuation())
-        .map(|item| item.coord)
-        .collect::<HashSet<Coord>>();
-    let nums = items_to_nums(&items);
-    let nums_adjacent_to_symbol: Vec<&Number> = nums
-        .iter()
-        .filter(|num| {
-            num.coords
-                .iter()
-                .any(|c| coord_is_adjacent_to_symbol(&symbol_coords, c))
-        })
-        .collect();
-    nums_adjacent_to_symbol.iter().map(|num| num.n as u32).sum()
-}
-
-fn coord_adjacent_numbers(coord: &Coord, numbers: &Vec<Number>) -> Vec<Number> {
-    let adjacent_numbers: HashSet<&Number> = HashSet::from_iter(
-        ADJACENT_COORD_OFFSETS
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+struct PartNumber<'a> {
+    row: usize,
+    start: usize,
+    end: usize,
+    digits: &'a str,
+}
+
+impl PartNumber<'_> {
+    fn value(&self) -> Decimal {
+        Decimal::parse(self.digits)
+    }
+}
+
+#[derive(PartialEq, Eq, Clone, Copy, Debug)]
+struct Symbol {
+    row: usize,
+    col: usize,
+    kind: char,
+}
+
+fn is_symbol(c: char) -> bool {
+    c != '.' && c.is_ascii_punctuation()
+}
+
+/// Numbers are tokenised per row, so a number can never run on into the next line,
+/// and kept as their digit text so their size is not limited by any integer type.
+struct Schematic<'a> {
+    numbers: Vec<PartNumber<'a>>,
+    symbols: Vec<Symbol>,
+    /// Indices into `numbers` adjacent to each entry of `symbols`.
+    adjacent: Vec<Vec<usize>>,
+}
+
+impl<'a> Schematic<'a> {
+    fn parse(data: &'a str) -> Schematic<'a> {
+        let rows = data.split("\n").collect::<Vec<_>>();
+        let mut numbers = vec![];
+        let mut symbols = vec![];
+        let mut number_at: Vec<Vec<Option<usize>>> = vec![];
+        for (row, line) in rows.iter().enumerate() {
+            let mut cells = vec![None; line.len()];
+            let mut start = None;
+            for (col, c) in line.char_indices().chain([(line.len(), '.')]) {
+                match (c.is_ascii_digit(), start) {
+                    (true, None) => start = Some(col),
+                    (false, Some(s)) => {
+                        cells[s..col].fill(Some(numbers.len()));
+                        numbers.push(PartNumber {
+                            row,
+                            start: s,
+                            end: col,
+                            digits: &line[s..col],
+                        });
+                        start = None;
+                    }
+                    _ => {}
+                }
+                if is_symbol(c) && col < line.len() {
+                    symbols.push(Symbol { row, col, kind: c });
+                }
+            }
+            number_at.push(cells);
+        }
+        let adjacent = symbols
             .iter()
-            .filter(|(x, y)| !((coord.x == 0 && *x < 0) || (coord.y == 0 && *y < 0)))
-            .map(|(x_offset, y_offset)| Coord {
-                x: (coord.x as i16 + x_offset) as usize,
-                y: (coord.y as i16 + y_offset) as usize,
-            })
-            .flat_map(|c| {
-                numbers
-                    .iter()
-                    .filter(|n| n.coords.contains(&c))
-                    .collect::<Vec<&Number>>()
+            .map(|s| {
+                let mut ns = (s.row.saturating_sub(1)..=s.row + 1)
+                    .filter_map(|r| number_at.get(r))
+                    .flat_map(|cells| {
+                        (s.col.saturating_sub(1)..=s.col + 1)
+                            .filter_map(|c| cells.get(c).copied().flatten())
+                    })
+                    .collect::<Vec<_>>();
+                ns.dedup();
+                ns
             })
-            .into_iter(),
-    );
-    adjacent_numbers
-        .into_iter()
-        .cloned()
-        .collect::<Vec<Number>>()
+            .collect();
+        Schematic {
+            numbers,
+            symbols,
+            adjacent,
+        }
+    }
+
+    /// Numbers adjacent to at least one symbol, each reported once.
+    fn part_numbers(&self) -> Vec<&PartNumber<'a>> {
+        let mut is_part = vec![false; self.numbers.len()];
+        self.adjacent
+            .iter()
+            .flatten()
+            .for_each(|n| is_part[*n] = true);
+        self.numbers
+            .iter()
+            .zip(is_part)
+            .filter(|(_, p)| *p)
+            .map(|(n, _)| n)
+            .collect()
+    }
+
+    fn symbols_of_kind(&self, kind: char) -> impl Iterator<Item = (&Symbol, Vec<&PartNumber<'a>>)> {
+        self.symbols
+            .iter()
+            .zip(&self.adjacent)
+            .filter(move |(s, _)| s.kind == kind)
+            .map(|(s, ns)| (s, ns.iter().map(|n| &self.numbers[*n]).collect()))
+    }
 }
 
-fn solve2(data: &str) -> u32 {
-    let items: Vec<Item> = parse(data)
+fn solve1(data: &str) -> Decimal {
+    Schematic::parse(data)
+        .part_numbers()
         .iter()
-        .enumerate()
-        .flat_map(|(y, l)| {
-            l.chars().enumerate().map(move |(x, c)| Item {
-                coord: Coord { y, x },
-                char: c,
-            })
-        })
-        .collect();
-    let nums = items_to_nums(&items);
-    let gear_candidates: Vec<&Item> = items
-        .iter()
-        .filter(|item| item.char == '*')
-        .collect::<Vec<&Item>>();
-    gear_candidates
-        .iter()
-        .map(|gc| coord_adjacent_numbers(&gc.coord, &nums))
-        .filter(|ns| ns.len() == 2)
-        .map(|ns| {
-            ns.iter()
-                .map(|n| n.n as u32)
-                .reduce(|acc, n| acc * n)
-                .unwrap()
-        })
-        .reduce(|acc, n| n + acc)
-        .unwrap()
+        .map(|n| n.value())
+        .sum()
+}
+
+fn solve2(data: &str) -> Decimal {
+    Schematic::parse(data)
+        .symbols_of_kind('*')
+        .filter(|(_, ns)| ns.len() == 2)
+        .map(|(_, ns)| ns.iter().map(|n| n.value()).product::<Decimal>())
+        .sum()
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let data = String::from_utf8_lossy(data);
+    let schematic = Schematic::parse(&data);
+    schematic.part_numbers();
+    schematic.symbols_of_kind('*').for_each(drop);
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -164,13 +247,79 @@ mod tests {
     #[test]
     fn test_part1() {
         let data = include_str!("../example.txt");
-        assert_eq!(solve1(data), 4361)
+        assert_eq!(solve1(data).to_string(), "4361")
     }
 
     #[test]
     fn test_part2() {
         let data = include_str!("../example.txt");
-        assert_eq!(solve2(data), 467835)
+        assert_eq!(solve2(data).to_string(), "467835")
+    }
+
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data).to_string(), "550934")
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data).to_string(), "81997870")
+    }
+
+    #[test]
+    fn test_numbers_do_not_span_lines() {
+        let schematic = Schematic::parse("..12\n34#.\n...56");
+        let digits = schematic
+            .numbers
+            .iter()
+            .map(|n| (n.row, n.start, n.end, n.digits))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            digits,
+            vec![(0, 2, 4, "12"), (1, 0, 2, "34"), (2, 3, 5, "56")]
+        );
+        assert_eq!(solve1("..12\n34#.\n...56"), Decimal::parse("102"));
+    }
+
+    #[test]
+    fn test_symbol_kinds_and_large_numbers() {
+        let data = "12345678901234567890.\n....................%\n.......7&8...........";
+        let schematic = Schematic::parse(data);
+        let percent = schematic.symbols_of_kind('%').collect::<Vec<_>>();
+        assert_eq!(percent.len(), 1);
+        assert_eq!(percent[0].1[0].value().to_string(), "12345678901234567890");
+        let ampersand = schematic.symbols_of_kind('&').collect::<Vec<_>>();
+        assert_eq!(
+            ampersand[0].1.iter().map(|n| n.value()).collect::<Vec<_>>(),
+            vec![Decimal::parse("7"), Decimal::parse("8")]
+        );
+    }
+
+    #[test]
+    fn test_numbers_beyond_u128() {
+        let a = "123456789012345678901234567890123456789012345";
+        let b = "999999999999999999999999999999999999999999999999";
+        let data = format!("{}*{}\n", a, b);
+        assert_eq!(
+            solve1(&data).to_string(),
+            "1000123456789012345678901234567890123456789012344"
+        );
+        assert_eq!(
+            solve2(&data).to_string(),
+            "123456789012345678901234567890123456789012344999876543210987654321098765432109876543210987655"
+        );
+        assert_eq!(Decimal::parse("000").to_string(), "0");
+        assert_eq!(
+            (Decimal::parse("1000000000") * Decimal::parse("0")).to_string(),
+            "0"
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
     }
 
     #[bench]
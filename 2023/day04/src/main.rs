@@ -1,91 +1,150 @@
 #![feature(test)]
 
-use std::collections::{HashMap, HashSet};
+use std::collections::VecDeque;
+use std::fmt;
+use std::io::{self, BufRead};
 
 extern crate test;
 
-fn parse(data: &str) -> Vec<&str> {
-    data.split("\n").collect()
+#[derive(Debug)]
+enum CardError {
+    Io(io::Error),
+    Malformed(String),
+    NumberOutOfRange(String),
+    PointsOverflow,
+    CopiesOverflow,
 }
 
-#[derive(Debug, Clone)]
+impl fmt::Display for CardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CardError::Io(e) => write!(f, "failed to read cards: {}", e),
+            CardError::Malformed(s) => write!(f, "malformed card: {}", s),
+            CardError::NumberOutOfRange(n) => write!(f, "number {} does not fit a 128-bit mask", n),
+            CardError::PointsOverflow => write!(f, "total points do not fit a u128"),
+            CardError::CopiesOverflow => write!(f, "scratchcard copies do not fit a usize"),
+        }
+    }
+}
+
+impl From<io::Error> for CardError {
+    fn from(e: io::Error) -> Self {
+        CardError::Io(e)
+    }
+}
+
+/// Numbers are stored as bits of a `u128`, so every number must be below 128.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 struct Card {
     id: usize,
-    winning_numbers: Vec<u8>,
-    numbers: Vec<u8>,
+    winning_numbers: u128,
+    numbers: u128,
+}
+
+fn parse_mask(s: &str) -> Result<u128, CardError> {
+    s.split_ascii_whitespace()
+        .try_fold(0u128, |mask, n| match n.parse::<u32>() {
+            Ok(n) if n < 128 => Ok(mask | 1 << n),
+            Ok(_) => Err(CardError::NumberOutOfRange(n.to_string())),
+            Err(_) => Err(CardError::Malformed(n.to_string())),
+        })
 }
 
 impl Card {
-    fn parse(s: &str) -> Card {
-        let (card_part, rest) = s.split_once(":").unwrap();
+    fn parse(s: &str) -> Result<Card, CardError> {
+        let malformed = || CardError::Malformed(s.to_string());
+        let (card_part, rest) = s.split_once(":").ok_or_else(malformed)?;
         let id = card_part
             .split_ascii_whitespace()
             .last()
-            .map(|s| s.parse::<usize>())
-            .unwrap()
-            .unwrap();
-        let (winning_numbers_str, numbers_str) = rest.split_once("|").unwrap();
-        let winning_numbers = winning_numbers_str
-            .split_ascii_whitespace()
-            .map(|n| n.parse::<u8>().unwrap())
-            .collect();
-        let numbers = numbers_str
-            .split_ascii_whitespace()
-            .map(|n| n.parse::<u8>().unwrap())
-            .collect();
-        Card {
+            .and_then(|s| s.parse::<usize>().ok())
+            .ok_or_else(malformed)?;
+        let (winning_numbers_str, numbers_str) = rest.split_once("|").ok_or_else(malformed)?;
+        Ok(Card {
             id,
-            winning_numbers,
-            numbers,
-        }
+            winning_numbers: parse_mask(winning_numbers_str)?,
+            numbers: parse_mask(numbers_str)?,
+        })
     }
 
-    fn count_points(&self) -> usize {
-        let winning_set: HashSet<u8> = self.winning_numbers.clone().into_iter().collect();
-        let numbers_set: HashSet<u8> = self.numbers.clone().into_iter().collect();
-        numbers_set
-            .intersection(&winning_set)
-            .fold(0, |acc, _| if acc == 0 { 1 } else { acc * 2 })
+    fn matches(&self) -> usize {
+        (self.winning_numbers & self.numbers).count_ones() as usize
     }
 
-    fn won_cards(&self) -> Vec<usize> {
-        let start = self.id + 1;
-        let winning_set: HashSet<u8> = self.winning_numbers.clone().into_iter().collect();
-        let numbers_set: HashSet<u8> = self.numbers.clone().into_iter().collect();
-        let matching_cards_count = numbers_set.intersection(&winning_set).count();
-        (start..start + matching_cards_count).collect()
+    /// A card has at most 128 matches, so its points always fit a `u128`.
+    fn count_points(&self) -> u128 {
+        match self.matches() {
+            0 => 0,
+            m => 1 << (m - 1),
+        }
     }
 }
 
-fn solve1(data: &str) -> usize {
-    parse(data)
-        .iter()
-        .map(|l| Card::parse(l))
-        .map(|c| c.count_points())
-        .sum()
+/// Parses cards one line at a time, so the whole pile never has to be in memory.
+fn read_cards<R: BufRead>(reader: R) -> impl Iterator<Item = Result<Card, CardError>> {
+    reader.lines().filter_map(|line| match line {
+        Ok(l) if l.trim().is_empty() => None,
+        Ok(l) => Some(Card::parse(&l)),
+        Err(e) => Some(Err(e.into())),
+    })
+}
+
+/// Each card adds its copies to the next `matches` cards. Only those pending copies
+/// are kept, in a ring buffer indexed from the current card.
+fn count_scratchcards<I: IntoIterator<Item = Result<Card, CardError>>>(
+    cards: I,
+) -> Result<usize, CardError> {
+    let mut pending: VecDeque<usize> = VecDeque::new();
+    let mut total = 0;
+    for card in cards {
+        let matches = card?.matches();
+        let copies = pending
+            .pop_front()
+            .unwrap_or(0)
+            .checked_add(1)
+            .ok_or(CardError::CopiesOverflow)?;
+        total = copies.checked_add(total).ok_or(CardError::CopiesOverflow)?;
+        if pending.len() < matches {
+            pending.resize(matches, 0);
+        }
+        for p in pending.iter_mut().take(matches) {
+            *p = p.checked_add(copies).ok_or(CardError::CopiesOverflow)?;
+        }
+    }
+    Ok(total)
+}
+
+fn total_points<I: IntoIterator<Item = Result<Card, CardError>>>(
+    cards: I,
+) -> Result<u128, CardError> {
+    cards.into_iter().try_fold(0u128, |total, card| {
+        total
+            .checked_add(card?.count_points())
+            .ok_or(CardError::PointsOverflow)
+    })
+}
+
+fn solve1(data: &str) -> u128 {
+    total_points(read_cards(data.as_bytes())).unwrap_or_else(|e| panic!("{}", e))
 }
 
 fn solve2(data: &str) -> usize {
-    let cards = parse(data)
-        .iter()
-        .map(|l| Card::parse(l))
-        .collect::<Vec<Card>>();
-    let mut card_id_copies: HashMap<usize, usize> =
-        HashMap::from_iter(cards.iter().map(|c| (c.id, 1)));
-    cards.iter().for_each(|c| {
-        c.won_cards().iter().for_each(|w| {
-            let curr_card_copy_count = card_id_copies.get(&c.id).unwrap();
-            let prev = card_id_copies.get(w).unwrap();
-            card_id_copies.insert(*w, prev + curr_card_copy_count);
-        })
-    });
-    card_id_copies.iter().map(|(_, won_times)| won_times).sum()
+    count_scratchcards(read_cards(data.as_bytes())).unwrap_or_else(|e| panic!("{}", e))
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = total_points(read_cards(data));
+    let _ = count_scratchcards(read_cards(data));
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -105,6 +164,75 @@ mod tests {
         assert_eq!(solve2(data), 30)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 20117)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 13768818)
+    }
+
+    #[test]
+    fn test_card_masks() {
+        let card = Card::parse("Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53").unwrap();
+        assert_eq!(card.matches(), 4);
+        assert_eq!(card.count_points(), 8);
+        assert!(matches!(
+            Card::parse("Card 2: 1 128 | 1"),
+            Err(CardError::NumberOutOfRange(_))
+        ));
+        assert!(matches!(
+            Card::parse("Card 3 1 2"),
+            Err(CardError::Malformed(_))
+        ));
+    }
+
+    #[test]
+    fn test_points_beyond_64_matches() {
+        let all = (0..128)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        let card = format!("Card 1: {} | {}", all, all);
+        assert_eq!(Card::parse(&card).unwrap().count_points(), 1 << 127);
+        assert!(matches!(
+            total_points(read_cards(format!("{}\n{}", card, card).as_bytes())),
+            Err(CardError::PointsOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_copies_overflow() {
+        let all = (0..128)
+            .map(|n| n.to_string())
+            .collect::<Vec<_>>()
+            .join(" ");
+        // Every card wins a copy of all later ones, so copies double per card.
+        let cards = (0..=usize::BITS)
+            .map(|id| format!("Card {}: {} | {}", id, all, all))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert!(matches!(
+            count_scratchcards(read_cards(cards.as_bytes())),
+            Err(CardError::CopiesOverflow)
+        ));
+    }
+
+    #[test]
+    fn test_read_cards_from_reader() {
+        let reader = io::BufReader::with_capacity(8, include_str!("../example.txt").as_bytes());
+        assert_eq!(count_scratchcards(read_cards(reader)).unwrap(), 30);
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
@@ -1,127 +1,292 @@
 #![feature(test)]
 
-use rayon::prelude::*;
+use std::collections::{HashMap, VecDeque};
 use std::ops::Range;
 
 extern crate test;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum AlmanacError {
+    MissingSeeds,
+    MalformedHeader(String),
+    MalformedEntry(String),
+    OverlappingEntries(String),
+    UnknownCategory(String),
+    NoConversion(String, String),
+    /// A range that runs past `u64::MAX`.
+    RangeOverflow(String),
+}
+
 #[derive(Debug, Clone)]
 struct MapEntry {
-    dest_range_start: usize,
-    source_range_start: usize,
-    range_length: usize,
+    dest_range_start: u64,
+    source_range_start: u64,
+    range_length: u64,
 }
 
 impl MapEntry {
-    fn parse(s: &str) -> MapEntry {
-        let mut it = s.split_ascii_whitespace();
-        let dest_range_start = it.next().unwrap().parse::<usize>().unwrap();
-        let source_range_start = it.next().unwrap().parse::<usize>().unwrap();
-        let range_length = it.next().unwrap().parse::<usize>().unwrap();
-        MapEntry {
-            dest_range_start,
-            source_range_start,
-            range_length,
+    fn parse(s: &str) -> Result<MapEntry, AlmanacError> {
+        let nums = s
+            .split_ascii_whitespace()
+            .map(|n| n.parse::<u64>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|_| AlmanacError::MalformedEntry(s.to_string()))?;
+        match nums[..] {
+            [dest_range_start, source_range_start, range_length] => {
+                if dest_range_start
+                    .max(source_range_start)
+                    .checked_add(range_length)
+                    .is_none()
+                {
+                    return Err(AlmanacError::RangeOverflow(s.to_string()));
+                }
+                Ok(MapEntry {
+                    dest_range_start,
+                    source_range_start,
+                    range_length,
+                })
+            }
+            _ => Err(AlmanacError::MalformedEntry(s.to_string())),
         }
     }
+
+    fn source(&self) -> Range<u64> {
+        self.source_range_start..self.source_range_start + self.range_length
+    }
+
+    fn dest(&self) -> Range<u64> {
+        self.dest_range_start..self.dest_range_start + self.range_length
+    }
 }
 
-fn parse(data: &str) -> (Vec<usize>, Vec<Vec<MapEntry>>) {
-    let segments = data
-        .split("\n\n")
-        .map(|s| s.split("\n").collect::<Vec<_>>())
-        .collect::<Vec<_>>();
-    let seeds = segments
-        .first()
-        .unwrap()
-        .first()
-        .unwrap()
-        .split_once(": ")
-        .unwrap()
-        .1
-        .split_ascii_whitespace()
-        .map(|n| n.trim().parse::<usize>().unwrap())
-        .collect::<Vec<usize>>();
-    let maps = segments
-        .iter()
-        .map(|s| {
-            s.iter()
-                .skip(1)
-                .filter(|s| !s.is_empty())
-                .map(|s| MapEntry::parse(s))
-                .collect::<Vec<_>>()
-        })
-        .collect::<Vec<_>>();
-    (seeds, maps)
+#[derive(Debug, Clone)]
+struct CategoryMap {
+    source: String,
+    destination: String,
+    entries: Vec<MapEntry>,
 }
 
-fn solve1(data: &str) -> usize {
-    let (seeds, maps) = parse(data);
-    seeds
-        .iter()
-        .map(|seed| {
-            maps.iter().fold(*seed, |prev, map| {
-                map.iter()
-                    .find(|entry| {
-                        prev >= entry.source_range_start
-                            && prev < entry.source_range_start + entry.range_length
-                    })
-                    .map(|entry| {
-                        let range_idx = prev - entry.source_range_start;
-                        entry.dest_range_start + range_idx
-                    })
-                    .unwrap_or(prev)
-            })
-        })
-        .min()
-        .unwrap()
+fn intersect(a: &Range<u64>, b: &Range<u64>) -> Option<Range<u64>> {
+    let r = a.start.max(b.start)..a.end.min(b.end);
+    (!r.is_empty()).then_some(r)
+}
+
+fn merge_ranges(mut ranges: Vec<Range<u64>>) -> Vec<Range<u64>> {
+    ranges.sort_by_key(|r| r.start);
+    let mut merged: Vec<Range<u64>> = vec![];
+    for r in ranges.into_iter().filter(|r| !r.is_empty()) {
+        match merged.last_mut() {
+            Some(last) if r.start <= last.end => last.end = last.end.max(r.end),
+            _ => merged.push(r),
+        }
+    }
+    merged
+}
+
+fn find_overlap(mut ranges: Vec<Range<u64>>) -> Option<Range<u64>> {
+    ranges.sort_by_key(|r| r.start);
+    ranges.windows(2).find_map(|w| intersect(&w[0], &w[1]))
 }
 
-fn parse2(data: &str) -> (Vec<Range<usize>>, Vec<Vec<MapEntry>>) {
-    let (seeds, maps) = parse(data);
-    let seed_ranges: Vec<Range<usize>> = seeds
-        .chunks_exact(2)
-        .map(|c| {
-            let start = *c.get(0).unwrap();
-            let length = *c.get(1).unwrap();
-            Range {
-                start,
-                end: start + length,
+impl CategoryMap {
+    fn parse(segment: &str) -> Result<CategoryMap, AlmanacError> {
+        let mut lines = segment.split("\n").filter(|l| !l.is_empty());
+        let header = lines.next().unwrap_or_default();
+        let (source, destination) = header
+            .strip_suffix(" map:")
+            .and_then(|h| h.split_once("-to-"))
+            .ok_or_else(|| AlmanacError::MalformedHeader(header.to_string()))?;
+        let entries = lines.map(MapEntry::parse).collect::<Result<Vec<_>, _>>()?;
+        let map = CategoryMap {
+            source: source.to_string(),
+            destination: destination.to_string(),
+            entries,
+        };
+        let overlapping_source = find_overlap(map.entries.iter().map(|e| e.source()).collect());
+        let overlapping_dest = find_overlap(map.entries.iter().map(|e| e.dest()).collect());
+        if overlapping_source.is_some() || overlapping_dest.is_some() {
+            return Err(AlmanacError::OverlappingEntries(header.to_string()));
+        }
+        Ok(map)
+    }
+
+    /// Values outside every source range map to themselves. Going backward yields
+    /// the full preimage: the untouched part of `range` plus every source range whose
+    /// destination intersects it.
+    fn convert(&self, range: &Range<u64>, backward: bool) -> Vec<Range<u64>> {
+        let mut converted = vec![];
+        let mut uncovered = vec![range.clone()];
+        for entry in &self.entries {
+            uncovered = uncovered
+                .iter()
+                .flat_map(|r| {
+                    let s = entry.source();
+                    [r.start..r.end.min(s.start), r.start.max(s.end)..r.end]
+                })
+                .filter(|r| !r.is_empty())
+                .collect();
+            let (from, to) = if backward {
+                (entry.dest(), entry.source())
+            } else {
+                (entry.source(), entry.dest())
+            };
+            if let Some(hit) = intersect(range, &from) {
+                converted.push(hit.start - from.start + to.start..hit.end - from.start + to.start);
             }
-        })
-        .collect();
-    (seed_ranges, maps)
+        }
+        converted.extend(uncovered);
+        converted
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Almanac {
+    seeds: Vec<u64>,
+    maps: Vec<CategoryMap>,
 }
 
-fn solve2(data: &str) -> usize {
-    let (seed_ranges, maps) = parse2(data);
-    seed_ranges
+impl Almanac {
+    fn parse(data: &str) -> Result<Almanac, AlmanacError> {
+        let mut segments = data.split("\n\n");
+        let seeds = segments
+            .next()
+            .and_then(|s| s.trim().strip_prefix("seeds:"))
+            .ok_or(AlmanacError::MissingSeeds)?
+            .split_ascii_whitespace()
+            .map(|n| {
+                n.parse::<u64>()
+                    .map_err(|_| AlmanacError::MalformedEntry(n.to_string()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let maps = segments
+            .filter(|s| !s.trim().is_empty())
+            .map(CategoryMap::parse)
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(Almanac { seeds, maps })
+    }
+
+    fn seed_ranges(&self) -> Result<Vec<Range<u64>>, AlmanacError> {
+        self.seeds
+            .chunks_exact(2)
+            .map(|c| {
+                c[0].checked_add(c[1])
+                    .map(|end| c[0]..end)
+                    .ok_or_else(|| AlmanacError::RangeOverflow(format!("{} {}", c[0], c[1])))
+            })
+            .collect()
+    }
+
+    /// Breadth-first search over the categories named in the map headers, where each
+    /// map can be followed in either direction. Returns map indices and whether each
+    /// one is applied backward.
+    fn conversion_path(&self, from: &str, to: &str) -> Result<Vec<(usize, bool)>, AlmanacError> {
+        let mut edges: HashMap<&str, Vec<(usize, bool, &str)>> = HashMap::new();
+        for (i, m) in self.maps.iter().enumerate() {
+            edges
+                .entry(&m.source)
+                .or_default()
+                .push((i, false, &m.destination));
+            edges
+                .entry(&m.destination)
+                .or_default()
+                .push((i, true, &m.source));
+        }
+        for category in [from, to] {
+            if !edges.contains_key(category) {
+                return Err(AlmanacError::UnknownCategory(category.to_string()));
+            }
+        }
+        let mut came_from: HashMap<&str, Option<(usize, bool, &str)>> =
+            HashMap::from([(from, None)]);
+        let mut queue = VecDeque::from([from]);
+        while let Some(category) = queue.pop_front() {
+            if category == to {
+                let mut path = vec![];
+                let mut curr = to;
+                while let Some((i, backward, prev)) = came_from[curr] {
+                    path.push((i, backward));
+                    curr = prev;
+                }
+                path.reverse();
+                return Ok(path);
+            }
+            for (i, backward, next) in &edges[category] {
+                if !came_from.contains_key(next) {
+                    came_from.insert(next, Some((*i, *backward, category)));
+                    queue.push_back(next);
+                }
+            }
+        }
+        Err(AlmanacError::NoConversion(from.to_string(), to.to_string()))
+    }
+
+    fn convert_ranges(
+        &self,
+        ranges: &[Range<u64>],
+        from: &str,
+        to: &str,
+    ) -> Result<Vec<Range<u64>>, AlmanacError> {
+        let path = self.conversion_path(from, to)?;
+        Ok(path
+            .iter()
+            .fold(merge_ranges(ranges.to_vec()), |ranges, (i, backward)| {
+                merge_ranges(
+                    ranges
+                        .iter()
+                        .flat_map(|r| self.maps[*i].convert(r, *backward))
+                        .collect(),
+                )
+            }))
+    }
+
+    /// All values in `to` that correspond to `value` in `from`. Converting forward
+    /// always gives exactly one value, backward may give none or several.
+    fn convert(&self, value: u64, from: &str, to: &str) -> Result<Vec<u64>, AlmanacError> {
+        let end = value
+            .checked_add(1)
+            .ok_or_else(|| AlmanacError::RangeOverflow(value.to_string()))?;
+        Ok(self
+            .convert_ranges(std::slice::from_ref(&(value..end)), from, to)?
+            .into_iter()
+            .flatten()
+            .collect())
+    }
+}
+
+fn solve1(data: &str) -> u64 {
+    let almanac = Almanac::parse(data).unwrap();
+    almanac
+        .seeds
         .iter()
-        .map(|sr| {
-            sr.clone()
-                .into_par_iter()
-                .map(|seed| {
-                    maps.iter().fold(seed, |prev, map| {
-                        map.iter()
-                            .find(|entry| {
-                                prev >= entry.source_range_start
-                                    && prev < entry.source_range_start + entry.range_length
-                            })
-                            .map(|entry| prev - entry.source_range_start + entry.dest_range_start)
-                            .unwrap_or(prev)
-                    })
-                })
-                .min()
-                .unwrap()
-        })
+        .flat_map(|seed| almanac.convert(*seed, "seed", "location").unwrap())
         .min()
         .unwrap()
 }
 
+fn solve2(data: &str) -> u64 {
+    let almanac = Almanac::parse(data).unwrap();
+    almanac
+        .convert_ranges(&almanac.seed_ranges().unwrap(), "seed", "location")
+        .unwrap()
+        .first()
+        .unwrap()
+        .start
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    if let Ok(almanac) = Almanac::parse(&String::from_utf8_lossy(data)) {
+        let _ = almanac.seed_ranges();
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -141,15 +306,118 @@ mod tests {
         assert_eq!(solve2(data), 46)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 600279879)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 20191102)
+    }
+
+    #[test]
+    fn test_convert_between_any_categories() {
+        let almanac = Almanac::parse(include_str!("../example.txt")).unwrap();
+        assert_eq!(almanac.convert(79, "seed", "soil"), Ok(vec![81]));
+        assert_eq!(almanac.convert(79, "seed", "location"), Ok(vec![82]));
+        assert_eq!(almanac.convert(82, "location", "seed"), Ok(vec![79]));
+        assert_eq!(almanac.convert(81, "soil", "fertilizer"), Ok(vec![81]));
+        assert_eq!(almanac.convert(81, "fertilizer", "seed"), Ok(vec![79]));
+        let seeds = almanac
+            .convert_ranges(&[46..48, 82..83], "location", "seed")
+            .unwrap();
+        assert!(seeds.iter().any(|r| r.contains(&82)));
+        assert_eq!(
+            almanac.convert(0, "seed", "dragon"),
+            Err(AlmanacError::UnknownCategory("dragon".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_maps_in_any_order() {
+        let data = "seeds: 5\n\nsoil-to-water map:\n100 0 10\n\nseed-to-soil map:\n0 5 1";
+        let almanac = Almanac::parse(data).unwrap();
+        assert_eq!(almanac.convert(5, "seed", "water"), Ok(vec![100]));
+        // soil 0 is reached from both seed 0 and seed 5, and water 100 from soil 100 too
+        assert_eq!(almanac.convert(100, "water", "seed"), Ok(vec![0, 5, 100]));
+    }
+
+    #[test]
+    fn test_overlapping_entries() {
+        let data = "seeds: 5\n\nseed-to-soil map:\n0 5 3\n10 7 3";
+        assert_eq!(
+            Almanac::parse(data).unwrap_err(),
+            AlmanacError::OverlappingEntries("seed-to-soil map:".to_string())
+        );
+    }
+
+    #[test]
+    fn test_single_seed_ranges_match_part1() {
+        for seed in 0..20 {
+            let data = aoc::gen::almanac(&mut aoc::gen::Rng::new(seed), 8);
+            let almanac = Almanac::parse(&data).unwrap();
+            let (_, maps) = data.split_once("\n\n").unwrap();
+            let starts = almanac
+                .seed_ranges()
+                .unwrap()
+                .iter()
+                .map(|r| r.start)
+                .collect::<Vec<_>>();
+            let as_seeds = |seeds: Vec<String>| format!("seeds: {}\n\n{}", seeds.join(" "), maps);
+            let part1 = solve1(&as_seeds(starts.iter().map(|s| s.to_string()).collect()));
+            let part2 = solve2(&as_seeds(
+                starts.iter().map(|s| format!("{} 1", s)).collect(),
+            ));
+            assert_eq!(part1, part2, "seed {}", seed);
+            assert!(solve2(&data) <= part1, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_range_overflow() {
+        let data = "seeds: 1\n\na-to-b map:\n0 18446744073709551615 2";
+        assert_eq!(
+            Almanac::parse(data).unwrap_err(),
+            AlmanacError::RangeOverflow("0 18446744073709551615 2".to_string())
+        );
+        let almanac = Almanac::parse("seeds: 18446744073709551615 1").unwrap();
+        assert_eq!(
+            almanac.seed_ranges(),
+            Err(AlmanacError::RangeOverflow(
+                "18446744073709551615 1".to_string()
+            ))
+        );
+        assert_eq!(
+            almanac.convert(u64::MAX, "seed", "seed"),
+            Err(AlmanacError::RangeOverflow(
+                "18446744073709551615".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(
+            fuzz_parse,
+            &[
+                include_bytes!("../example.txt"),
+                include_bytes!("../../../fuzz/corpus/y2023_day05/range-overflow.txt"),
+            ],
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
         b.iter(|| solve1(data));
     }
 
-    // #[bench]
-    // fn bench_part2(b: &mut Bencher) {
-    //     let data = include_str!("../input.txt");
-    //     b.iter(|| solve2(data));
-    // }
+    #[bench]
+    fn bench_part2(b: &mut Bencher) {
+        let data = include_str!("../input.txt");
+        b.iter(|| solve2(data));
+    }
 }
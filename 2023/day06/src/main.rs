@@ -2,114 +2,188 @@
 
 extern crate test;
 
+use std::ops::RangeInclusive;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum RaceError {
+    MissingLine(&'static str),
+    MalformedNumber(String),
+    MismatchedColumns { times: usize, distances: usize },
+    TimeTooLarge(u128),
+}
+
 #[derive(Debug, Clone, Copy)]
 struct Race {
-    time: usize,
-    best_distance: usize,
+    time: u128,
+    best_distance: u128,
+}
+
+/// Largest `x` for which `x * (x + 1) <= n`.
+fn max_pronic_root(n: u128) -> u128 {
+    let x = n.isqrt();
+    match x.checked_mul(x + 1) {
+        Some(p) if p <= n => x,
+        _ => x - 1,
+    }
 }
 
 impl Race {
-    fn get_button_hold_times_to_win(&self) -> Vec<usize> {
-        let upward = 1..self.time;
-        let downward = (1..self.time).rev();
-        let range_start = upward
-            .clone()
-            .find(|time_pressed| {
-                let speed = time_pressed;
-                let distance = (self.time - time_pressed) * speed;
-                distance > self.best_distance
-            })
-            .unwrap();
-        let range_end = downward
-            .clone()
-            .find(|time_pressed| {
-                let speed = time_pressed;
-                let distance = (self.time - time_pressed) * speed;
-                distance > self.best_distance
-            })
-            .unwrap();
-        (range_start..=range_end).collect()
+    /// Holding the button for `h` ms travels `h * (time - h)`, a parabola peaking at
+    /// `time / 2`. Winning holds form a contiguous range symmetric around the peak,
+    /// so its half-width is the largest offset `x` still beating the record:
+    /// `m² - x² > d` for even times and `m(m + 1) - x(x + 1) > d` for odd ones.
+    ///
+    /// `Ok(None)` when nothing beats the record, and an error when the peak distance
+    /// itself does not fit a `u128`.
+    fn winning_hold_times(&self) -> Result<Option<RangeInclusive<u128>>, RaceError> {
+        let half = self.time / 2;
+        let odd = self.time % 2;
+        let peak_distance = (half + odd)
+            .checked_mul(half)
+            .ok_or(RaceError::TimeTooLarge(self.time))?;
+        if peak_distance <= self.best_distance {
+            return Ok(None);
+        }
+        let margin = peak_distance - self.best_distance - 1;
+        if odd == 0 {
+            let x = margin.isqrt();
+            Ok(Some(half - x..=half + x))
+        } else {
+            let x = max_pronic_root(margin);
+            Ok(Some(half - x..=half + 1 + x))
+        }
+    }
+
+    fn earliest_winning_hold_time(&self) -> Result<Option<u128>, RaceError> {
+        Ok(self.winning_hold_times()?.map(|r| *r.start()))
+    }
+
+    fn latest_winning_hold_time(&self) -> Result<Option<u128>, RaceError> {
+        Ok(self.winning_hold_times()?.map(|r| *r.end()))
+    }
+
+    fn count_winning_hold_times(&self) -> Result<u128, RaceError> {
+        Ok(self
+            .winning_hold_times()?
+            .map_or(0, |r| r.end() - r.start() + 1))
     }
 }
 
-fn parse(data: &str) -> Vec<Race> {
-    let lines = data.split("\n").collect::<Vec<_>>();
-    let times = lines
-        .first()
-        .unwrap()
-        .split(":")
-        .last()
-        .unwrap()
-        .split_ascii_whitespace()
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect::<Vec<_>>();
-    let distance = lines
-        .last()
-        .unwrap()
-        .split(":")
-        .last()
-        .unwrap()
+/// The text after the `Time:` and `Distance:` headers.
+fn columns(data: &str) -> Result<(&str, &str), RaceError> {
+    let mut lines = data.lines();
+    let mut column = |header| {
+        lines
+            .next()
+            .and_then(|l| l.strip_prefix(header))
+            .ok_or(RaceError::MissingLine(header))
+    };
+    Ok((column("Time:")?, column("Distance:")?))
+}
+
+fn numbers(column: &str) -> Result<Vec<u128>, RaceError> {
+    column
         .split_ascii_whitespace()
-        .filter(|s| !s.is_empty())
-        .map(|s| s.parse::<usize>().unwrap())
-        .collect::<Vec<_>>();
-    times
+        .map(|s| {
+            s.parse::<u128>()
+                .map_err(|_| RaceError::MalformedNumber(s.to_string()))
+        })
+        .collect()
+}
+
+fn parse(data: &str) -> Result<Vec<Race>, RaceError> {
+    let (times, distances) = columns(data)?;
+    let (times, distances) = (numbers(times)?, numbers(distances)?);
+    if times.len() != distances.len() {
+        return Err(RaceError::MismatchedColumns {
+            times: times.len(),
+            distances: distances.len(),
+        });
+    }
+    Ok(times
         .iter()
-        .zip(distance)
+        .zip(distances)
         .map(|(t, d)| Race {
             time: *t,
             best_distance: d,
         })
-        .collect::<Vec<_>>()
+        .collect())
 }
 
-fn solve1(data: &str) -> usize {
-    let races = parse(data);
+fn solve1(data: &str) -> u128 {
+    let races = parse(data).unwrap();
     races
         .iter()
-        .map(|r| r.get_button_hold_times_to_win().iter().count())
-        .reduce(|acc, curr| acc * curr)
-        .unwrap()
+        .map(|r| r.count_winning_hold_times().unwrap())
+        .product()
 }
 
-fn parse2(data: &str) -> Race {
-    let lines = data.split("\n").collect::<Vec<_>>();
-    let time_line = lines.first().unwrap();
-    let distance_line = lines.last().unwrap();
-    let time = time_line
-        .split_once(":")
-        .unwrap()
-        .1
-        .chars()
-        .filter(|c| !c.is_ascii_whitespace())
-        .collect::<String>()
-        .parse::<usize>()
-        .unwrap();
-    let distance = distance_line
-        .split_once(":")
-        .unwrap()
-        .1
-        .chars()
-        .filter(|c| !c.is_ascii_whitespace())
-        .collect::<String>()
-        .parse::<usize>()
-        .unwrap();
-    Race {
-        time,
-        best_distance: distance,
+/// Reads each line as one number, ignoring the spaces between its digits.
+fn parse2(data: &str) -> Result<Race, RaceError> {
+    let (time, distance) = columns(data)?;
+    let number = |column: &str| {
+        let digits = column
+            .chars()
+            .filter(|c| !c.is_ascii_whitespace())
+            .collect::<String>();
+        digits
+            .parse::<u128>()
+            .map_err(|_| RaceError::MalformedNumber(digits))
+    };
+    Ok(Race {
+        time: number(time)?,
+        best_distance: number(distance)?,
+    })
+}
+
+fn solve2(data: &str) -> u128 {
+    let race = parse2(data).unwrap();
+    race.count_winning_hold_times().unwrap()
+}
+
+/// Tries every hold time, with none of the closed form's arithmetic to get wrong.
+#[cfg(test)]
+mod reference {
+    fn count_wins(race: &super::Race) -> u128 {
+        (0..=race.time)
+            .filter(|h| h * (race.time - h) > race.best_distance)
+            .count() as u128
+    }
+
+    pub fn solve1(data: &str) -> u128 {
+        super::parse(data).unwrap().iter().map(count_wins).product()
+    }
+
+    pub fn solve2(data: &str) -> u128 {
+        count_wins(&super::parse2(data).unwrap())
     }
 }
 
-fn solve2(data: &str) -> usize {
-    let race = parse2(data);
-    race.get_button_hold_times_to_win().len()
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let data = String::from_utf8_lossy(data);
+    for race in parse(&data).into_iter().flatten().chain(parse2(&data)) {
+        let _ = race.count_winning_hold_times();
+    }
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
+    let race = parse2(data).unwrap();
+    match (
+        race.earliest_winning_hold_time().unwrap(),
+        race.latest_winning_hold_time().unwrap(),
+    ) {
+        (Some(earliest), Some(latest)) => {
+            println!("Winning holds: {} ms to {} ms", earliest, latest)
+        }
+        _ => println!("No hold beats the record"),
+    }
 }
 
 #[cfg(test)]
@@ -141,6 +215,86 @@ mod tests {
         assert_eq!(solve2(data), 20537782)
     }
 
+    #[test]
+    fn test_record_distance_does_not_win() {
+        let race = Race {
+            time: 30,
+            best_distance: 200,
+        };
+        assert_eq!(race.winning_hold_times(), Ok(Some(11..=19)));
+        let race = Race {
+            time: 30,
+            best_distance: 225,
+        };
+        assert_eq!(race.winning_hold_times(), Ok(None));
+        assert_eq!(race.count_winning_hold_times(), Ok(0));
+    }
+
+    #[test]
+    fn test_earliest_and_latest_winning_hold_time() {
+        let race = Race {
+            time: 7,
+            best_distance: 9,
+        };
+        assert_eq!(race.earliest_winning_hold_time(), Ok(Some(2)));
+        assert_eq!(race.latest_winning_hold_time(), Ok(Some(5)));
+        assert_eq!(race.count_winning_hold_times(), Ok(4));
+    }
+
+    #[test]
+    fn test_times_beyond_64_bits() {
+        let time = u64::MAX as u128 + 3;
+        let half = time / 2;
+        let race = Race {
+            time,
+            best_distance: half * half - 1,
+        };
+        assert_eq!(race.winning_hold_times(), Ok(Some(half..=half)));
+        let race = Race {
+            time: u128::MAX,
+            best_distance: 0,
+        };
+        assert_eq!(
+            race.winning_hold_times(),
+            Err(RaceError::TimeTooLarge(u128::MAX))
+        );
+        assert_eq!(
+            race.earliest_winning_hold_time(),
+            Err(RaceError::TimeTooLarge(u128::MAX))
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse("Time: 7 15\nDistance: 9").unwrap_err(),
+            RaceError::MismatchedColumns {
+                times: 2,
+                distances: 1
+            }
+        );
+        assert_eq!(
+            parse2("Time: 7 1x5\nDistance: 9").unwrap_err(),
+            RaceError::MalformedNumber("71x5".to_string())
+        );
+        assert_eq!(
+            parse("Time: 7").unwrap_err(),
+            RaceError::MissingLine("Distance:")
+        );
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        aoc::check::assert_agree(aoc::gen::races, 6, 0..200, solve1, reference::solve1);
+        // Part 2 glues the races together, so keep them few enough to brute force.
+        aoc::check::assert_agree(aoc::gen::races, 2, 0..200, solve2, reference::solve2);
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
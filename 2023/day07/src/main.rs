@@ -10,6 +10,12 @@ enum JokerHandling {
     Jokers,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HandError {
+    Malformed(String),
+    InvalidCard(char),
+}
+
 #[derive(Debug, Clone, Copy, Eq)]
 struct Hand {
     hand: [u8; 5],
@@ -148,34 +154,35 @@ impl HandType {
 }
 
 impl Hand {
-    fn parse(s: &str, jokers: JokerHandling) -> Hand {
-        let (hand_str, bid_str) = s.split_once(" ").unwrap();
+    fn parse(s: &str, jokers: JokerHandling) -> Result<Hand, HandError> {
+        let malformed = || HandError::Malformed(s.to_string());
+        let (hand_str, bid_str) = s.split_once(" ").ok_or_else(malformed)?;
         let hand: [u8; 5] = hand_str
             .chars()
-            .map(|c| c.to_string())
-            .map(|c| match c.as_str() {
-                "T" => 10,
-                "J" => match jokers {
-                    JokerHandling::NoJokers => 11,
-                    JokerHandling::Jokers => 1,
+            .map(|c| match c {
+                'T' => Ok(10),
+                'J' => match jokers {
+                    JokerHandling::NoJokers => Ok(11),
+                    JokerHandling::Jokers => Ok(1),
                 },
-                "Q" => 12,
-                "K" => 13,
-                "A" => 14,
-                _ => c.parse::<u8>().unwrap(),
+                'Q' => Ok(12),
+                'K' => Ok(13),
+                'A' => Ok(14),
+                '2'..='9' => Ok(c as u8 - b'0'),
+                _ => Err(HandError::InvalidCard(c)),
             })
-            .collect::<Vec<u8>>()
+            .collect::<Result<Vec<u8>, _>>()?
             .try_into()
-            .unwrap();
-        Hand {
-            hand: hand,
+            .map_err(|_| malformed())?;
+        Ok(Hand {
+            hand,
             hand_type: match jokers {
                 JokerHandling::NoJokers => HandType::detect(hand),
                 JokerHandling::Jokers => HandType::detect_with_jokers(hand),
             },
-            bid: bid_str.parse::<usize>().unwrap(),
+            bid: bid_str.parse::<usize>().map_err(|_| malformed())?,
             jokers,
-        }
+        })
     }
 }
 impl Ord for Hand {
@@ -211,15 +218,15 @@ impl PartialEq for Hand {
     }
 }
 
-fn parse(data: &str, jokers: JokerHandling) -> Vec<Hand> {
+fn parse(data: &str, jokers: JokerHandling) -> Result<Vec<Hand>, HandError> {
     data.split("\n")
         .filter(|l| !l.is_empty())
         .map(|l| Hand::parse(l, jokers))
-        .collect::<Vec<_>>()
+        .collect()
 }
 
 fn solve1(data: &str) -> usize {
-    let mut hands = parse(data, JokerHandling::NoJokers);
+    let mut hands = parse(data, JokerHandling::NoJokers).unwrap();
     hands.sort();
     hands
         .iter()
@@ -230,7 +237,7 @@ fn solve1(data: &str) -> usize {
 }
 
 fn solve2(data: &str) -> usize {
-    let mut hands = parse(data, JokerHandling::Jokers);
+    let mut hands = parse(data, JokerHandling::Jokers).unwrap();
     hands.sort();
     hands
         .iter()
@@ -240,10 +247,83 @@ fn solve2(data: &str) -> usize {
         .fold(0, |a, b| a + b)
 }
 
+/// Ranks hands by their sorted card counts and plays jokers by trying every card in
+/// their place, instead of the hand-written joker table.
+#[cfg(test)]
+mod reference {
+    const CARDS: &str = "23456789TJQKA";
+
+    fn strength(hand: &str) -> usize {
+        let mut counts = CARDS
+            .chars()
+            .map(|card| hand.chars().filter(|c| *c == card).count())
+            .filter(|n| *n > 0)
+            .collect::<Vec<_>>();
+        counts.sort_by(|a, b| b.cmp(a));
+        match counts.as_slice() {
+            [5] => 7,
+            [4, ..] => 6,
+            [3, 2] => 5,
+            [3, ..] => 4,
+            [2, 2, ..] => 3,
+            [2, ..] => 2,
+            _ => 1,
+        }
+    }
+
+    fn winnings(data: &str, jokers: bool) -> usize {
+        let order = if jokers { "J23456789TQKA" } else { CARDS };
+        let mut hands = data
+            .lines()
+            .map(|l| {
+                let (hand, bid) = l.split_once(' ').unwrap();
+                let best = if jokers {
+                    CARDS
+                        .chars()
+                        .map(|c| strength(&hand.replace('J', &c.to_string())))
+                        .max()
+                        .unwrap()
+                } else {
+                    strength(hand)
+                };
+                let cards = hand
+                    .chars()
+                    .map(|c| order.find(c).unwrap())
+                    .collect::<Vec<_>>();
+                ((best, cards), bid.parse::<usize>().unwrap())
+            })
+            .collect::<Vec<_>>();
+        hands.sort();
+        hands
+            .iter()
+            .enumerate()
+            .map(|(rank, (_, bid))| (rank + 1) * bid)
+            .sum()
+    }
+
+    pub fn solve1(data: &str) -> usize {
+        winnings(data, false)
+    }
+
+    pub fn solve2(data: &str) -> usize {
+        winnings(data, true)
+    }
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let data = String::from_utf8_lossy(data);
+    let _ = parse(&data, JokerHandling::NoJokers);
+    let _ = parse(&data, JokerHandling::Jokers);
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -275,6 +355,41 @@ mod tests {
         assert_eq!(solve2(data), 247899149);
     }
 
+    #[test]
+    fn test_order_of_hands_does_not_matter() {
+        for seed in 0..20 {
+            let mut rng = aoc::gen::Rng::new(seed);
+            let data = aoc::gen::camel_cards(&mut rng, 50);
+            let mut lines = data.lines().collect::<Vec<_>>();
+            rng.shuffle(&mut lines);
+            let shuffled = lines.join("\n");
+            assert_eq!(solve1(&data), solve1(&shuffled), "seed {}", seed);
+            assert_eq!(solve2(&data), solve2(&shuffled), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |s| Hand::parse(s, JokerHandling::Jokers).unwrap_err();
+        assert_eq!(parse("32T3K"), HandError::Malformed("32T3K".to_string()));
+        assert_eq!(
+            parse("32T3 765"),
+            HandError::Malformed("32T3 765".to_string())
+        );
+        assert_eq!(parse("32T1K 765"), HandError::InvalidCard('1'));
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        aoc::check::assert_agree(aoc::gen::camel_cards, 30, 0..200, solve1, reference::solve1);
+        aoc::check::assert_agree(aoc::gen::camel_cards, 30, 0..200, solve2, reference::solve2);
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
@@ -4,105 +4,263 @@ extern crate test;
 
 use std::collections::HashMap;
 
-fn solve1(data: &str) -> usize {
-    let (dirs_str, nodes_str) = data.split_once("\n\n").unwrap();
-    let nodes = nodes_str
-        .split("\n")
-        .map(|l| {
-            let (id, dir_ids_str) = l.split_once(" = ").unwrap();
-            let dir_ids = dir_ids_str
-                .chars()
-                .filter(|c| c.is_alphabetic() || c.is_ascii_whitespace())
-                .collect::<String>();
-            let (left, right) = dir_ids.split_once(" ").unwrap();
-            (id, (left.clone().to_owned(), right.clone().to_owned()))
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum NetworkError {
+    MissingInstructions,
+    MissingNodes,
+    InvalidInstruction(char),
+    MalformedNode(String),
+    UnknownNode(String),
+}
+
+/// Nodes are interned to indices in input order; `left` and `right` hold the index of
+/// the node each direction leads to.
+struct Network<'a> {
+    instructions: Vec<bool>,
+    names: Vec<&'a str>,
+    left: Vec<usize>,
+    right: Vec<usize>,
+}
+
+/// The walk from one start node, seen as states `(node, instruction offset)`. The
+/// state first seen at step `start` recurs every `length` steps, so a hit at step `h`
+/// inside the cycle also happens at every `h + k * length`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct GhostCycle {
+    start: usize,
+    length: usize,
+    hits_before_cycle: Vec<usize>,
+    hits_in_cycle: Vec<usize>,
+}
+
+impl<'a> Network<'a> {
+    fn parse(data: &'a str) -> Result<Network<'a>, NetworkError> {
+        let (dirs_str, nodes_str) = data.split_once("\n\n").ok_or(NetworkError::MissingNodes)?;
+        let instructions = dirs_str
+            .trim()
+            .chars()
+            .map(|d| match d {
+                'L' => Ok(true),
+                'R' => Ok(false),
+                _ => Err(NetworkError::InvalidInstruction(d)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if instructions.is_empty() {
+            return Err(NetworkError::MissingInstructions);
+        }
+        let edges = nodes_str
+            .split("\n")
+            .filter(|l| !l.is_empty())
+            .map(|l| {
+                let malformed = || NetworkError::MalformedNode(l.to_string());
+                let (id, dir_ids_str) = l.split_once(" = ").ok_or_else(malformed)?;
+                let (left, right) = dir_ids_str
+                    .strip_prefix('(')
+                    .and_then(|s| s.strip_suffix(')'))
+                    .and_then(|s| s.split_once(", "))
+                    .ok_or_else(malformed)?;
+                Ok((id, left, right))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let index = edges
+            .iter()
+            .enumerate()
+            .map(|(i, (id, _, _))| (*id, i))
+            .collect::<HashMap<_, _>>();
+        let lookup = |id: &str| {
+            index
+                .get(id)
+                .copied()
+                .ok_or_else(|| NetworkError::UnknownNode(id.to_string()))
+        };
+        Ok(Network {
+            instructions,
+            names: edges.iter().map(|(id, _, _)| *id).collect(),
+            left: edges
+                .iter()
+                .map(|(_, l, _)| lookup(l))
+                .collect::<Result<_, _>>()?,
+            right: edges
+                .iter()
+                .map(|(_, _, r)| lookup(r))
+                .collect::<Result<_, _>>()?,
         })
-        .collect::<HashMap<_, _>>();
-    let mut node = "AAA";
-    dirs_str
-        .as_bytes()
-        .iter()
-        .cycle()
-        .enumerate()
-        .find(|(_, direction)| {
-            let (left, right) = nodes.get(node).unwrap();
-            if **direction == b'L' {
-                node = left;
-            } else {
-                node = right;
+    }
+
+    fn nodes_matching(&self, predicate: impl Fn(&str) -> bool) -> Vec<usize> {
+        (0..self.names.len())
+            .filter(|n| predicate(self.names[*n]))
+            .collect()
+    }
+
+    fn next(&self, node: usize, step: usize) -> usize {
+        if self.instructions[step % self.instructions.len()] {
+            self.left[node]
+        } else {
+            self.right[node]
+        }
+    }
+
+    fn steps(&self, start: &str, is_end: impl Fn(&str) -> bool) -> Option<usize> {
+        let node = self.names.iter().position(|n| *n == start)?;
+        let cycle = self.ghost_cycle(node, &is_end);
+        cycle
+            .hits_before_cycle
+            .first()
+            .or(cycle.hits_in_cycle.first())
+            .copied()
+    }
+
+    fn ghost_cycle(&self, start: usize, is_end: impl Fn(&str) -> bool) -> GhostCycle {
+        let period = self.instructions.len();
+        let mut first_seen: Vec<Option<usize>> = vec![None; self.names.len() * period];
+        let mut hits = vec![];
+        let mut node = start;
+        let mut step = 0;
+        loop {
+            let state = node * period + step % period;
+            if let Some(cycle_start) = first_seen[state] {
+                let (before, within) = hits.iter().partition(|h| **h < cycle_start);
+                return GhostCycle {
+                    start: cycle_start,
+                    length: step - cycle_start,
+                    hits_before_cycle: before,
+                    hits_in_cycle: within,
+                };
             }
-            node == "ZZZ"
-        })
-        .map(|(count, _)| count + 1)
-        .unwrap()
+            first_seen[state] = Some(step);
+            if step > 0 && is_end(self.names[node]) {
+                hits.push(step);
+            }
+            node = self.next(node, step);
+            step += 1;
+        }
+    }
+
+    /// First step at which every ghost stands on an end node at once. Steps before all
+    /// ghosts are inside their cycles are simulated directly, later ones are found by
+    /// combining every choice of in-cycle hit with the Chinese remainder theorem, so
+    /// cycles need not be aligned to step zero.
+    fn ghost_steps(
+        &self,
+        is_start: impl Fn(&str) -> bool,
+        is_end: impl Fn(&str) -> bool + Copy,
+    ) -> Option<u128> {
+        let starts = self.nodes_matching(is_start);
+        let cycles = starts
+            .iter()
+            .map(|s| self.ghost_cycle(*s, is_end))
+            .collect::<Vec<_>>();
+        let settled = cycles.iter().map(|c| c.start).max()?;
+
+        let mut nodes = starts.clone();
+        for step in 1..=settled {
+            nodes = nodes.iter().map(|n| self.next(*n, step - 1)).collect();
+            if nodes.iter().all(|n| is_end(self.names[*n])) {
+                return Some(step as u128);
+            }
+        }
+
+        cycles
+            .iter()
+            .fold(vec![(0i128, 1i128)], |congruences, cycle| {
+                congruences
+                    .iter()
+                    .flat_map(|c| {
+                        cycle
+                            .hits_in_cycle
+                            .iter()
+                            .filter_map(|h| combine(*c, (*h as i128, cycle.length as i128)))
+                    })
+                    .collect()
+            })
+            .iter()
+            .map(|(r, m)| {
+                let settled = settled as i128 + 1;
+                let r = r.rem_euclid(*m);
+                if r >= settled {
+                    r
+                } else {
+                    r + (settled - r + m - 1) / m * m
+                }
+            })
+            .min()
+            .map(|t| t as u128)
+    }
+}
+
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a, 1, 0)
+    } else {
+        let (g, x, y) = ext_gcd(b, a % b);
+        (g, y, x - (a / b) * y)
+    }
 }
 
-fn gcd(n1: usize, n2: usize) -> usize {
-    let mut a = n1;
-    let mut b = n2;
-    let mut t = 0usize;
-    while b != 0 {
-        t = b;
-        b = a % b;
-        a = t;
+/// Merges `t ≡ r1 (mod m1)` and `t ≡ r2 (mod m2)` into a single congruence, if any `t`
+/// satisfies both.
+fn combine((r1, m1): (i128, i128), (r2, m2): (i128, i128)) -> Option<(i128, i128)> {
+    let (g, p, _) = ext_gcd(m1, m2);
+    if (r2 - r1) % g != 0 {
+        return None;
     }
-    a
+    let lcm = m1 / g * m2;
+    let k = ((r2 - r1) / g * p).rem_euclid(m2 / g);
+    Some(((r1 + m1 * k).rem_euclid(lcm), lcm))
 }
 
-fn lcm(a: usize, b: usize) -> usize {
-    (a * b) / gcd(a, b)
+fn solve1(data: &str) -> usize {
+    Network::parse(data)
+        .unwrap()
+        .steps("AAA", |n| n == "ZZZ")
+        .unwrap()
 }
 
-fn solve2(data: &str) -> usize {
-    let (dirs_str, nodes_str) = data.split_once("\n\n").unwrap();
-    let nodes = nodes_str
-        .split("\n")
-        .map(|l| {
-            let (id, dir_ids_str) = l.split_once(" = ").unwrap();
-            let dir_ids = dir_ids_str
-                .chars()
-                .filter(|c| c.is_ascii_alphanumeric() || c.is_ascii_whitespace())
-                .collect::<String>();
-            let (left, right) = dir_ids.split_once(" ").unwrap();
-            (id, (left.clone().to_owned(), right.clone().to_owned()))
-        })
-        .collect::<HashMap<_, _>>();
-    let starts = nodes
-        .iter()
-        .map(|(id, _)| id)
-        .filter(|node| node.ends_with("A"))
-        .collect::<Vec<_>>();
-    starts
-        .iter()
-        .map(|id| {
-            let mut node = **id;
-            dirs_str
-                .as_bytes()
-                .iter()
-                .cycle()
-                .enumerate()
-                .find(|(_, direction)| {
-                    let (left, right) = nodes
-                        .get(node)
-                        .unwrap_or_else(|| panic!("Tried to get node {}", node));
-                    if **direction == b'L' {
-                        node = left;
-                    } else {
-                        node = right;
-                    }
-                    node.ends_with("Z")
-                })
-                .map(|(count, _)| count + 1)
-                .unwrap()
-        })
-        .reduce(|a, b| lcm(a, b))
+fn solve2(data: &str) -> u128 {
+    Network::parse(data)
         .unwrap()
+        .ghost_steps(|n| n.ends_with("A"), |n| n.ends_with("Z"))
+        .unwrap()
+}
+
+/// Walks every ghost in lockstep until they all stand on an end node, or until the
+/// joint state of all ghosts and the instruction offset repeats, in which case they
+/// never will.
+#[cfg(test)]
+mod reference {
+    use std::collections::HashSet;
+
+    pub fn ghost_steps(data: &str) -> Option<u128> {
+        let network = super::Network::parse(data).unwrap();
+        let is_end = |n: &usize| network.names[*n].ends_with('Z');
+        let mut nodes = network.nodes_matching(|n| n.ends_with('A'));
+        let mut seen = HashSet::new();
+        for step in 0.. {
+            if step > 0 && nodes.iter().all(is_end) {
+                return Some(step as u128);
+            }
+            if !seen.insert((nodes.clone(), step % network.instructions.len())) {
+                return None;
+            }
+            nodes = nodes.iter().map(|n| network.next(*n, step)).collect();
+        }
+        unreachable!()
+    }
+}
+
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    let _ = Network::parse(&String::from_utf8_lossy(data));
 }
 
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -124,6 +282,95 @@ mod tests {
         assert_eq!(solve2(data), 6)
     }
 
+    #[test]
+    fn test_part1_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve1(data), 19199)
+    }
+
+    #[test]
+    fn test_part2_actual() {
+        let data = include_str!("../input.txt");
+        assert_eq!(solve2(data), 13663968099527)
+    }
+
+    #[test]
+    fn test_ghost_cycle() {
+        let network = Network::parse(include_str!("../example3.txt")).unwrap();
+        let start = network.nodes_matching(|n| n == "22A")[0];
+        assert_eq!(
+            network.ghost_cycle(start, |n| n.ends_with("Z")),
+            GhostCycle {
+                start: 1,
+                length: 6,
+                hits_before_cycle: vec![],
+                hits_in_cycle: vec![3, 6],
+            }
+        );
+    }
+
+    #[test]
+    fn test_offset_cycles() {
+        // A reaches its end at steps 2, 5, 8, ... and B at steps 3, 7, 11, ...,
+        // so neither cycle is aligned to zero and plain LCM would answer 12.
+        let data = "L\n\n\
+            A0A = (A1X, A1X)\nA1X = (A2Z, A2Z)\nA2Z = (A3X, A3X)\nA3X = (A4X, A4X)\n\
+            A4X = (A2Z, A2Z)\n\
+            B0A = (B1X, B1X)\nB1X = (B2X, B2X)\nB2X = (B3Z, B3Z)\nB3Z = (B4X, B4X)\n\
+            B4X = (B5X, B5X)\nB5X = (B6X, B6X)\nB6X = (B3Z, B3Z)";
+        let network = Network::parse(data).unwrap();
+        assert_eq!(
+            network.ghost_steps(|n| n.ends_with("A"), |n| n.ends_with("Z")),
+            Some(11)
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let parse = |s| Network::parse(s).err();
+        assert_eq!(parse("LR"), Some(NetworkError::MissingNodes));
+        assert_eq!(
+            parse("LXR\n\nAAA = (AAA, AAA)"),
+            Some(NetworkError::InvalidInstruction('X'))
+        );
+        assert_eq!(
+            parse("L\n\nAAA = (AAA, BBB)"),
+            Some(NetworkError::UnknownNode("BBB".to_string()))
+        );
+        assert_eq!(
+            parse("L\n\nAAA = AAA, AAA"),
+            Some(NetworkError::MalformedNode("AAA = AAA, AAA".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_matches_reference() {
+        fn ghost_steps(data: &str) -> Option<u128> {
+            Network::parse(data)
+                .unwrap()
+                .ghost_steps(|n| n.ends_with("A"), |n| n.ends_with("Z"))
+        }
+        aoc::check::assert_agree(
+            aoc::gen::ghost_network,
+            8,
+            0..500,
+            ghost_steps,
+            reference::ghost_steps,
+        );
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(
+            fuzz_parse,
+            &[
+                include_bytes!("../example.txt"),
+                include_bytes!("../example2.txt"),
+                include_bytes!("../example3.txt"),
+            ],
+        );
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
@@ -0,0 +1,177 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExtrapolationError {
+    /// The sequence has fewer than two values, so it cannot confirm any degree.
+    TooShort,
+    /// The `n`-th differences never reach zero, so the values are not generated by a
+    /// polynomial of degree below `n - 1` and any extrapolation would be a guess.
+    NotPolynomial,
+    Overflow,
+}
+
+use ExtrapolationError::*;
+
+fn binomial(n: i128, k: usize) -> Result<i128, ExtrapolationError> {
+    // Generalised to any integer `n`: C(n, j + 1) = C(n, j) * (n - j) / (j + 1) stays
+    // exact because C(n, j) * (n - j) is always divisible by j + 1.
+    (0..k as i128).try_fold(1i128, |c, j| {
+        c.checked_mul(n - j).map(|p| p / (j + 1)).ok_or(Overflow)
+    })
+}
+
+/// The polynomial through a sequence sampled at x = 0, 1, ..., n - 1, kept in Newton
+/// form: P(x) = Σ Δᵏy₀ · C(x, k).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    forward_differences: Vec<i128>,
+    len: usize,
+}
+
+impl Polynomial {
+    pub fn fit(values: &[i64]) -> Result<Polynomial, ExtrapolationError> {
+        if values.len() < 2 {
+            return Err(TooShort);
+        }
+        // Δᵏy₀ = Σ (-1)^(k-i) · C(k, i) · yᵢ
+        let forward_differences = (0..values.len())
+            .map(|k| {
+                values[..=k]
+                    .iter()
+                    .enumerate()
+                    .try_fold(0i128, |acc, (i, y)| {
+                        let term = binomial(k as i128, i)?
+                            .checked_mul(*y as i128)
+                            .ok_or(Overflow)?;
+                        if (k - i) % 2 == 0 {
+                            acc.checked_add(term)
+                        } else {
+                            acc.checked_sub(term)
+                        }
+                        .ok_or(Overflow)
+                    })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        if *forward_differences.last().unwrap() != 0 {
+            return Err(NotPolynomial);
+        }
+        let degree = forward_differences
+            .iter()
+            .rposition(|d| *d != 0)
+            .unwrap_or(0);
+        Ok(Polynomial {
+            forward_differences: forward_differences[..=degree].to_vec(),
+            len: values.len(),
+        })
+    }
+
+    #[cfg(test)]
+    pub fn degree(&self) -> usize {
+        self.forward_differences.len() - 1
+    }
+
+    pub fn at(&self, x: i128) -> Result<i128, ExtrapolationError> {
+        self.forward_differences
+            .iter()
+            .enumerate()
+            .try_fold(0i128, |acc, (k, d)| {
+                binomial(x, k)?
+                    .checked_mul(*d)
+                    .and_then(|term| acc.checked_add(term))
+                    .ok_or(Overflow)
+            })
+    }
+
+    /// Value `steps` positions after the last sample.
+    pub fn forward(&self, steps: usize) -> Result<i128, ExtrapolationError> {
+        self.at(self.len as i128 - 1 + steps as i128)
+    }
+
+    /// Value `steps` positions before the first sample.
+    pub fn backward(&self, steps: usize) -> Result<i128, ExtrapolationError> {
+        self.at(-(steps as i128))
+    }
+
+    /// Monomial coefficients `c` and a common denominator `q` such that
+    /// P(x) = (c₀ + c₁x + c₂x² + ...) / q, reduced to lowest terms.
+    #[cfg(test)]
+    pub fn coefficients(&self) -> Result<(Vec<i128>, i128), ExtrapolationError> {
+        let degree = self.degree();
+        let denominator =
+            (1..=degree as i128).try_fold(1i128, |f, k| f.checked_mul(k).ok_or(Overflow))?;
+        let mut numerators = vec![0i128; degree + 1];
+        // falling[j] is the coefficient of x^j in x(x - 1)...(x - k + 1)
+        let mut falling = vec![1i128];
+        let mut factorial = 1i128;
+        for (k, d) in self.forward_differences.iter().enumerate() {
+            if k > 0 {
+                factorial = factorial.checked_mul(k as i128).ok_or(Overflow)?;
+                let shift = k as i128 - 1;
+                let mut next = vec![0i128; falling.len() + 1];
+                for (j, c) in falling.iter().enumerate() {
+                    next[j + 1] = next[j + 1].checked_add(*c).ok_or(Overflow)?;
+                    next[j] = c
+                        .checked_mul(shift)
+                        .and_then(|p| next[j].checked_sub(p))
+                        .ok_or(Overflow)?;
+                }
+                falling = next;
+            }
+            let scale = d.checked_mul(denominator / factorial).ok_or(Overflow)?;
+            for (n, c) in numerators.iter_mut().zip(&falling) {
+                *n = c
+                    .checked_mul(scale)
+                    .and_then(|p| n.checked_add(p))
+                    .ok_or(Overflow)?;
+            }
+        }
+        let divisor = numerators.iter().fold(denominator, |g, n| gcd(g, *n));
+        Ok((
+            numerators.iter().map(|n| n / divisor).collect(),
+            denominator / divisor,
+        ))
+    }
+}
+
+#[cfg(test)]
+fn gcd(a: i128, b: i128) -> i128 {
+    if b == 0 {
+        a.abs()
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extrapolate_both_ways() {
+        let p = Polynomial::fit(&[10, 13, 16, 21, 30, 45]).unwrap();
+        assert_eq!(p.degree(), 3);
+        assert_eq!(p.forward(1), Ok(68));
+        assert_eq!(p.backward(1), Ok(5));
+        assert_eq!(p.at(2), Ok(16));
+    }
+
+    #[test]
+    fn test_coefficients() {
+        let p = Polynomial::fit(&[1, 3, 6, 10, 15, 21]).unwrap();
+        // (x + 1)(x + 2) / 2
+        assert_eq!(p.coefficients(), Ok((vec![2, 3, 1], 2)));
+        let p = Polynomial::fit(&[7, 7, 7]).unwrap();
+        assert_eq!(p.coefficients(), Ok((vec![7], 1)));
+    }
+
+    #[test]
+    fn test_not_polynomial() {
+        assert_eq!(Polynomial::fit(&[1, 2, 4, 8, 16]), Err(NotPolynomial));
+        assert_eq!(Polynomial::fit(&[1]), Err(TooShort));
+    }
+
+    #[test]
+    fn test_overflow() {
+        let p = Polynomial::fit(&[-i64::MAX, 0, i64::MAX]).unwrap();
+        assert_eq!(p.degree(), 1);
+        assert_eq!(p.at(i128::MAX / 4), Err(Overflow));
+    }
+}
@@ -2,92 +2,62 @@
 
 extern crate test;
 
-trait AllZerosCheck {
-    fn all_zeros(&self) -> bool;
-}
+mod extrapolation;
 
-impl AllZerosCheck for Vec<i64> {
-    fn all_zeros(&self) -> bool {
-        self.iter().all(|n| *n == 0)
-    }
+use extrapolation::Polynomial;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum HistoryError {
+    MalformedValue(String),
 }
 
-fn parse_histories(data: &str) -> Vec<Vec<i64>> {
+fn parse_histories(data: &str) -> Result<Vec<Vec<i64>>, HistoryError> {
     data.split("\n")
         .filter(|l| !l.is_empty())
         .map(|l| {
             l.split_ascii_whitespace()
-                .map(|s| s.parse::<i64>().unwrap())
-                .collect::<Vec<_>>()
+                .map(|s| {
+                    s.parse::<i64>()
+                        .map_err(|_| HistoryError::MalformedValue(s.to_string()))
+                })
+                .collect::<Result<Vec<_>, _>>()
         })
-        .collect::<Vec<_>>()
+        .collect()
 }
 
-fn solve1(data: &str) -> usize {
-    let histories = parse_histories(data);
-    histories
+fn solve1(data: &str) -> i128 {
+    parse_histories(data)
+        .unwrap()
         .iter()
-        .map(|hs| {
-            let mut diff_seqs: Vec<Vec<i64>> = vec![hs.clone()];
-            diff_seqs.push(hs.windows(2).map(|w| w[1] - w[0]).collect());
-            loop {
-                let last = diff_seqs.last().unwrap();
-                if last.all_zeros() {
-                    break;
-                }
-                diff_seqs.push(last.windows(2).map(|w| w[1] - w[0]).collect());
-            }
-            diff_seqs
-        })
-        .map(|diffs| {
-            diffs
-                .iter()
-                .map(|ds| *ds.last().unwrap())
-                .reduce(|prev, curr| prev + curr)
-                .unwrap()
-        })
-        .reduce(|a, b| a + b)
-        .unwrap() as usize
+        .map(|h| Polynomial::fit(h).unwrap().forward(1).unwrap())
+        .sum()
 }
 
-fn solve2(data: &str) -> usize {
-    let reversed_histories = parse_histories(data)
+fn solve2(data: &str) -> i128 {
+    parse_histories(data)
+        .unwrap()
         .iter()
-        .map(|h| {
-            let mut to_reverse = h.clone();
-            to_reverse.reverse();
-            to_reverse
-        })
-        .collect::<Vec<Vec<i64>>>();
-    reversed_histories
-        .iter()
-        .map(|head| {
-            let mut diff_seqs: Vec<Vec<i64>> = vec![head.clone()];
-            diff_seqs.push(head.windows(2).map(|w| w[1] - w[0]).collect());
-            loop {
-                let last = diff_seqs.last().unwrap();
-                if last.all_zeros() {
-                    break;
-                }
-                diff_seqs.push(last.windows(2).map(|w| w[1] - w[0]).collect());
-            }
-            diff_seqs
-        })
-        .map(|diffs| {
-            diffs
-                .iter()
-                .map(|ds| *ds.last().unwrap())
-                .reduce(|prev, curr| prev + curr)
-                .unwrap()
-        })
-        .reduce(|a, b| a + b)
-        .unwrap() as usize
+        .map(|h| Polynomial::fit(h).unwrap().backward(1).unwrap())
+        .sum()
 }
 
+#[cfg(any(test, fuzzing))]
+pub(crate) fn fuzz_parse(data: &[u8]) {
+    for history in parse_histories(&String::from_utf8_lossy(data)).unwrap_or_default() {
+        if let Ok(polynomial) = Polynomial::fit(&history) {
+            let _ = polynomial.forward(1);
+            let _ = polynomial.backward(1);
+        }
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+
 fn main() {
     let data = include_str!("../input.txt");
-    println!("Part 1: {}", solve1(data));
-    println!("Part 2: {}", solve2(data));
+    aoc::report::part(1, || solve1(data));
+    aoc::report::part(2, || solve2(data));
 }
 
 #[cfg(test)]
@@ -111,6 +81,24 @@ mod tests {
         assert_eq!(solve2(actual_data), 925);
     }
 
+    #[test]
+    fn test_backward_is_forward_of_reversed() {
+        for seed in 0..20 {
+            let data = aoc::gen::histories(&mut aoc::gen::Rng::new(seed), 30);
+            let reversed = data
+                .lines()
+                .map(|l| l.split(' ').rev().collect::<Vec<_>>().join(" "))
+                .collect::<Vec<_>>()
+                .join("\n");
+            assert_eq!(solve2(&data), solve1(&reversed), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn test_fuzz_parse() {
+        aoc::fuzz::assert_no_panics(fuzz_parse, &[include_bytes!("../example.txt")]);
+    }
+
     #[bench]
     fn bench_part1(b: &mut Bencher) {
         let data = include_str!("../input.txt");
@@ -0,0 +1,127 @@
+//! A global allocator that counts what goes through it.
+//!
+//! A day opts in with
+//!
+//! ```ignore
+//! #[global_allocator]
+//! static ALLOCATOR: aoc::alloc::CountingAllocator = aoc::alloc::CountingAllocator;
+//! ```
+//!
+//! after which [`measure`] reports the allocations a closure made. The counters are
+//! process wide, so work on other threads during a measurement is counted too.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+static ALLOCATED_BYTES: AtomicUsize = AtomicUsize::new(0);
+static LIVE_BYTES: AtomicUsize = AtomicUsize::new(0);
+static PEAK_BYTES: AtomicUsize = AtomicUsize::new(0);
+
+/// The system allocator, counting allocations, bytes allocated and live bytes.
+pub struct CountingAllocator;
+
+fn record_allocation(size: usize) {
+    ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+    ALLOCATED_BYTES.fetch_add(size, Ordering::Relaxed);
+    let live = LIVE_BYTES.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK_BYTES.fetch_max(live, Ordering::Relaxed);
+}
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            record_allocation(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    /// Counts as freeing the old block and allocating the new one.
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            LIVE_BYTES.fetch_sub(layout.size(), Ordering::Relaxed);
+            record_allocation(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// What a measured closure cost. `peak_bytes` is the most memory it held at once on
+/// top of what was live when it started.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Usage {
+    pub elapsed: Duration,
+    pub allocations: usize,
+    pub allocated_bytes: usize,
+    pub peak_bytes: usize,
+}
+
+/// Runs `f` and returns its result with the time and memory it took. Everything is
+/// zero but the time unless [`CountingAllocator`] is the global allocator.
+pub fn measure<T>(f: impl FnOnce() -> T) -> (T, Usage) {
+    let live_before = LIVE_BYTES.load(Ordering::Relaxed);
+    PEAK_BYTES.store(live_before, Ordering::Relaxed);
+    let allocations_before = ALLOCATIONS.load(Ordering::Relaxed);
+    let bytes_before = ALLOCATED_BYTES.load(Ordering::Relaxed);
+    let start = Instant::now();
+    let result = f();
+    let elapsed = start.elapsed();
+    let usage = Usage {
+        elapsed,
+        allocations: ALLOCATIONS.load(Ordering::Relaxed) - allocations_before,
+        allocated_bytes: ALLOCATED_BYTES.load(Ordering::Relaxed) - bytes_before,
+        peak_bytes: PEAK_BYTES
+            .load(Ordering::Relaxed)
+            .saturating_sub(live_before),
+    };
+    (result, usage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[global_allocator]
+    static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+    #[test]
+    fn test_measure() {
+        let (sum, usage) = measure(|| {
+            let big = vec![1u8; 1 << 20];
+            let small = (0..100).map(|i| vec![i as u8; 10]).collect::<Vec<_>>();
+            big.len() + small.len()
+        });
+        assert_eq!(sum, (1 << 20) + 100);
+        // Other tests may allocate at the same time, so only lower bounds hold.
+        assert!(usage.allocations >= 102);
+        assert!(usage.allocated_bytes >= (1 << 20) + 1000);
+        assert!(usage.peak_bytes >= 1 << 20);
+    }
+
+    #[test]
+    fn test_realloc_counts_new_block() {
+        let (_, usage) = measure(|| {
+            let mut v: Vec<u64> = Vec::with_capacity(1);
+            v.extend(0..1000);
+            v
+        });
+        assert!(usage.allocations >= 2);
+        assert!(usage.peak_bytes >= 8000);
+    }
+}
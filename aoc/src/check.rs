@@ -0,0 +1,177 @@
+//! Differential testing: run a fast solver and a naive reference one on many generated
+//! inputs and insist they agree.
+//!
+//! When they don't, the failing input is shrunk before it is reported: first by
+//! generating it again with the same seed at smaller sizes, then by dropping runs of
+//! lines for as long as the solvers still disagree. Candidates the reference panics on
+//! are treated as invalid and skipped, while a panic in the fast solver counts as a
+//! disagreement.
+
+use std::fmt::{self, Debug, Display};
+use std::ops::Range;
+use std::panic::{self, UnwindSafe};
+
+use crate::gen::{Generator, Rng};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub seed: u64,
+    pub size: usize,
+    pub input: String,
+    pub fast: String,
+    pub reference: String,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(
+            f,
+            "fast and reference solvers disagree (seed {}, size {})",
+            self.seed, self.size
+        )?;
+        writeln!(f, "fast:      {}", self.fast)?;
+        writeln!(f, "reference: {}", self.reference)?;
+        write!(f, "shrunk input:\n{}", self.input)
+    }
+}
+
+fn run<T: Debug>(solve: impl FnOnce() -> T + UnwindSafe) -> Option<String> {
+    panic::catch_unwind(solve)
+        .ok()
+        .map(|out| format!("{:?}", out))
+}
+
+/// The two answers for `input`, if the reference accepts it and the fast solver
+/// disagrees with it.
+fn disagreement<T: PartialEq + Debug>(
+    input: &str,
+    fast: fn(&str) -> T,
+    reference: fn(&str) -> T,
+) -> Option<(String, String)> {
+    let expected = run(|| reference(input))?;
+    let actual = run(|| fast(input)).unwrap_or_else(|| "panicked".to_string());
+    (actual != expected).then_some((actual, expected))
+}
+
+fn shrink<T: PartialEq + Debug>(
+    mut input: String,
+    fast: fn(&str) -> T,
+    reference: fn(&str) -> T,
+) -> String {
+    let mut lines = input.split('\n').map(String::from).collect::<Vec<_>>();
+    let mut chunk = (lines.len() / 2).max(1);
+    loop {
+        let mut removed = false;
+        let mut i = 0;
+        while i < lines.len() {
+            let mut candidate = lines.clone();
+            candidate.drain(i..(i + chunk).min(lines.len()));
+            let joined = candidate.join("\n");
+            if disagreement(&joined, fast, reference).is_some() {
+                lines = candidate;
+                input = joined;
+                removed = true;
+            } else {
+                i += chunk;
+            }
+        }
+        if !removed {
+            if chunk == 1 {
+                return input;
+            }
+            chunk /= 2;
+        }
+    }
+}
+
+/// The first seed in `seeds` whose input of `size` makes the solvers disagree, with
+/// that input shrunk as far as it goes.
+pub fn find_mismatch<T: PartialEq + Debug>(
+    generate: Generator,
+    size: usize,
+    seeds: Range<u64>,
+    fast: fn(&str) -> T,
+    reference: fn(&str) -> T,
+) -> Option<Mismatch> {
+    let failing = |seed, size| {
+        let input = generate(&mut Rng::new(seed), size);
+        disagreement(&input, fast, reference).map(|_| input)
+    };
+    let seed = seeds
+        .into_iter()
+        .find(|seed| failing(*seed, size).is_some())?;
+    let (size, input) = (0..=size).find_map(|size| Some((size, failing(seed, size)?)))?;
+    let input = shrink(input, fast, reference);
+    let (fast, reference) = disagreement(&input, fast, reference)?;
+    Some(Mismatch {
+        seed,
+        size,
+        input,
+        fast,
+        reference,
+    })
+}
+
+/// Panics with the shrunk input if `fast` and `reference` disagree on any seed.
+pub fn assert_agree<T: PartialEq + Debug>(
+    generate: Generator,
+    size: usize,
+    seeds: Range<u64>,
+    fast: fn(&str) -> T,
+    reference: fn(&str) -> T,
+) {
+    if let Some(mismatch) = find_mismatch(generate, size, seeds, fast, reference) {
+        panic!("{}", mismatch);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gen;
+
+    fn sum(data: &str) -> i64 {
+        data.split_ascii_whitespace()
+            .map(|n| n.parse::<i64>().unwrap())
+            .sum()
+    }
+
+    fn sum_without_negatives(data: &str) -> i64 {
+        data.split_ascii_whitespace()
+            .map(|n| n.parse::<i64>().unwrap().max(0))
+            .sum()
+    }
+
+    #[test]
+    fn test_agreeing_solvers_pass() {
+        assert_agree(gen::histories, 20, 0..20, sum, sum);
+    }
+
+    #[test]
+    fn test_mismatch_is_shrunk() {
+        let mismatch = find_mismatch(gen::histories, 20, 0..20, sum_without_negatives, sum)
+            .expect("negative values are generated");
+        assert_eq!(mismatch.size, 1);
+        assert_eq!(mismatch.input.lines().count(), 1);
+        assert!(mismatch.input.contains('-'));
+        assert_ne!(mismatch.fast, mismatch.reference);
+    }
+
+    #[test]
+    fn test_fast_panic_is_a_mismatch() {
+        fn first(data: &str) -> i64 {
+            data.split_ascii_whitespace()
+                .next()
+                .unwrap()
+                .parse()
+                .unwrap()
+        }
+        fn first_or_panic(data: &str) -> i64 {
+            assert!(data.lines().count() < 3);
+            first(data)
+        }
+        let mismatch = find_mismatch(gen::histories, 10, 0..1, first_or_panic, first).unwrap();
+        assert_eq!(mismatch.fast, "panicked");
+        assert_eq!(mismatch.input.lines().count(), 3);
+    }
+}
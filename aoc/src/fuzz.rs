@@ -0,0 +1,274 @@
+//! Offline fuzzing for puzzle parsers.
+//!
+//! A target has the shape of a libFuzzer `fuzz_target!` body, `fn(&[u8])`, and must not
+//! panic on any input: parsers turn bad input into typed errors instead. Each day
+//! defines its target as `fuzz_parse`. Its tests run it through [`fuzz`], which mutates
+//! a corpus seeded from the day's example files with mutations modelled on libFuzzer's
+//! default ones and reports the first input that panics, shrunk as far as it still
+//! does. The `fuzz/` cargo-fuzz crate links the same targets against libFuzzer, for
+//! example `cargo +nightly fuzz run y2023_day07`.
+//!
+//! Two environment variables tune a run. `AOC_FUZZ_RUNS` sets the number of mutated
+//! inputs to try, for longer sessions than the default test run. `AOC_FUZZ_CORPUS` names
+//! a directory of extra inputs, one per file, such as a libFuzzer corpus; a crashing
+//! input is written there as `crash-<hash>`, the name libFuzzer gives its artifacts.
+
+use std::collections::hash_map::DefaultHasher;
+use std::fmt::{self, Display};
+use std::hash::{Hash, Hasher};
+use std::panic;
+use std::path::Path;
+use std::{env, fs};
+
+use crate::gen::Rng;
+
+pub type Target = fn(&[u8]);
+
+const DEFAULT_RUNS: usize = 3000;
+const MAX_LEN: usize = 4096;
+
+/// Byte strings that tend to break parsers: separators, signs, empty numbers and
+/// numbers too large for any integer type.
+const INTERESTING: &[&[u8]] = &[
+    b"\n",
+    b"\n\n",
+    b" ",
+    b",",
+    b":",
+    b"-",
+    b"0",
+    b"-1",
+    b"4294967295",
+    b"18446744073709551615",
+    b"18446744073709551616",
+    b"340282366920938463463374607431768211456",
+    b"\xff",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Crash {
+    pub input: Vec<u8>,
+    pub message: String,
+}
+
+impl Crash {
+    pub fn artifact_name(&self) -> String {
+        let mut hasher = DefaultHasher::new();
+        self.input.hash(&mut hasher);
+        format!("crash-{:016x}", hasher.finish())
+    }
+}
+
+impl Display for Crash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "fuzz target panicked: {}", self.message)?;
+        write!(
+            f,
+            "input ({} bytes): \"{}\"",
+            self.input.len(),
+            String::from_utf8_lossy(&self.input).escape_debug()
+        )
+    }
+}
+
+/// The panic message if `target` panics on `input`.
+fn run(target: Target, input: &[u8]) -> Option<String> {
+    let payload = panic::catch_unwind(|| target(input)).err()?;
+    Some(
+        payload
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| payload.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".to_string()),
+    )
+}
+
+fn mutate(rng: &mut Rng, input: &mut Vec<u8>, corpus: &[Vec<u8>]) {
+    let len = input.len();
+    let position = rng.below(len as u64 + 1) as usize;
+    match rng.below(6) {
+        0 if len > 0 => {
+            let start = rng.below(len as u64) as usize;
+            let end = start + rng.between(1, (len - start).min(16) as i64) as usize;
+            input.drain(start..end);
+        }
+        1 => input.insert(position, rng.below(256) as u8),
+        2 if len > 0 => {
+            let i = rng.below(len as u64) as usize;
+            input[i] ^= 1 << rng.below(8);
+        }
+        3 => {
+            let token = rng.pick(INTERESTING);
+            input.splice(position..position, token.iter().copied());
+        }
+        4 if len > 0 => {
+            let start = rng.below(len as u64) as usize;
+            let end = start + rng.between(1, (len - start).min(64) as i64) as usize;
+            let copied = input[start..end].to_vec();
+            input.splice(position..position, copied);
+        }
+        _ => {
+            let other = rng.pick(corpus);
+            let from = rng.below(other.len() as u64 + 1) as usize;
+            input.truncate(position);
+            input.extend_from_slice(&other[from..]);
+        }
+    }
+    input.truncate(MAX_LEN);
+}
+
+/// Drops runs of bytes from a crashing input for as long as it keeps crashing.
+fn minimize(target: Target, mut crash: Crash) -> Crash {
+    let mut chunk = (crash.input.len() / 2).max(1);
+    loop {
+        let mut removed = false;
+        let mut i = 0;
+        while i < crash.input.len() {
+            let mut candidate = crash.input.clone();
+            candidate.drain(i..(i + chunk).min(crash.input.len()));
+            match run(target, &candidate) {
+                Some(message) => {
+                    crash = Crash {
+                        input: candidate,
+                        message,
+                    };
+                    removed = true;
+                }
+                None => i += chunk,
+            }
+        }
+        if !removed {
+            if chunk == 1 {
+                return crash;
+            }
+            chunk /= 2;
+        }
+    }
+}
+
+fn load_corpus(dir: &Path) -> Vec<Vec<u8>> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_file())
+        .filter_map(|e| fs::read(e.path()).ok())
+        .collect()
+}
+
+/// Runs `target` on every corpus input, then on `runs` mutations of them, and returns
+/// the first crash.
+pub fn fuzz(target: Target, corpus: &[Vec<u8>], runs: usize, seed: u64) -> Option<Crash> {
+    let mut rng = Rng::new(seed);
+    let mut crash = corpus.iter().find_map(|input| {
+        run(target, input).map(|message| Crash {
+            input: input.clone(),
+            message,
+        })
+    });
+    let corpus = if corpus.is_empty() {
+        &[vec![]][..]
+    } else {
+        corpus
+    };
+    for _ in 0..runs {
+        if crash.is_some() {
+            break;
+        }
+        let mut input = rng.pick(corpus).clone();
+        for _ in 0..rng.between(1, 8) {
+            mutate(&mut rng, &mut input, corpus);
+        }
+        crash = run(target, &input).map(|message| Crash { input, message });
+    }
+    crash.map(|crash| minimize(target, crash))
+}
+
+/// Fuzzes `target` from the `seeds` plus anything in `AOC_FUZZ_CORPUS`, and panics with
+/// the minimized input if it ever panics.
+pub fn assert_no_panics(target: Target, seeds: &[&[u8]]) {
+    let runs = env::var("AOC_FUZZ_RUNS")
+        .ok()
+        .and_then(|r| r.parse().ok())
+        .unwrap_or(DEFAULT_RUNS);
+    let dir = env::var_os("AOC_FUZZ_CORPUS");
+    let mut corpus = seeds.iter().map(|s| s.to_vec()).collect::<Vec<_>>();
+    if let Some(dir) = &dir {
+        corpus.extend(load_corpus(Path::new(dir)));
+    }
+    if let Some(crash) = fuzz(target, &corpus, runs, 0) {
+        if let Some(dir) = &dir {
+            let _ = fs::write(Path::new(dir).join(crash.artifact_name()), &crash.input);
+        }
+        panic!("{}", crash);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sum(data: &[u8]) {
+        String::from_utf8_lossy(data)
+            .split_ascii_whitespace()
+            .map(|n| n.parse::<u32>().unwrap())
+            .sum::<u32>();
+    }
+
+    fn sum_checked(data: &[u8]) {
+        let _ = String::from_utf8_lossy(data)
+            .split_ascii_whitespace()
+            .map(|n| n.parse::<u32>())
+            .collect::<Result<Vec<_>, _>>();
+    }
+
+    #[test]
+    fn test_fuzz_finds_and_minimizes_panics() {
+        let corpus = vec![b"1 2 3\n4 5 6".to_vec()];
+        let crash = fuzz(sum, &corpus, 1000, 0).expect("some mutation is not a number");
+        for i in 0..crash.input.len() {
+            let mut smaller = crash.input.clone();
+            smaller.remove(i);
+            assert_eq!(
+                run(sum, &smaller),
+                None,
+                "{:?} was not minimized",
+                crash.input
+            );
+        }
+        assert!(crash.message.contains("ParseIntError"));
+        assert!(crash.artifact_name().starts_with("crash-"));
+    }
+
+    fn sum_unchecked(data: &[u8]) {
+        String::from_utf8_lossy(data)
+            .split_ascii_whitespace()
+            .filter_map(|n| n.parse::<u32>().ok())
+            .sum::<u32>();
+    }
+
+    #[test]
+    fn test_fuzz_finds_integer_overflow() {
+        let corpus = vec![b"1 2 3\n4 5 6".to_vec()];
+        let crash = fuzz(sum_unchecked, &corpus, 1000, 0).expect("a boundary token overflows");
+        assert!(crash.message.contains("overflow"), "{}", crash);
+    }
+
+    #[test]
+    fn test_fuzz_passes_typed_errors() {
+        let corpus = vec![b"1 2 3\n4 5 6".to_vec()];
+        assert_eq!(fuzz(sum_checked, &corpus, 1000, 0), None);
+    }
+
+    #[test]
+    fn test_mutations_stay_bounded() {
+        let corpus = vec![vec![b'x'; MAX_LEN]];
+        let mut rng = Rng::new(1);
+        for _ in 0..200 {
+            let mut input = corpus[0].clone();
+            mutate(&mut rng, &mut input, &corpus);
+            assert!(input.len() <= MAX_LEN);
+        }
+    }
+}
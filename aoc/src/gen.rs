@@ -0,0 +1,368 @@
+//! Random but valid puzzle inputs, for tests that check properties over many inputs
+//! rather than answers for one.
+//!
+//! Every generator takes an [`Rng`] and a `size`, roughly the number of lines it
+//! writes, and returns the input as text in the puzzle's own format. The same seed
+//! always gives the same input, so a failing case can be reproduced with
+//! `aoc gen <day> --seed <seed> --size <size>`.
+
+use std::collections::HashSet;
+use std::fmt::Write;
+
+/// SplitMix64: small, fast and good enough to build test inputs from.
+#[derive(Debug, Clone)]
+pub struct Rng(u64);
+
+impl Rng {
+    pub fn new(seed: u64) -> Self {
+        Rng(seed)
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0..n`; `n` must not be zero.
+    pub fn below(&mut self, n: u64) -> u64 {
+        assert!(n > 0, "below(0) has no values");
+        ((self.next_u64() as u128 * n as u128) >> 64) as u64
+    }
+
+    /// A number in `low..=high`.
+    pub fn between(&mut self, low: i64, high: i64) -> i64 {
+        low + self.below(high.abs_diff(low) + 1) as i64
+    }
+
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        &items[self.below(items.len() as u64) as usize]
+    }
+
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i as u64 + 1) as usize);
+        }
+    }
+}
+
+pub type Generator = fn(&mut Rng, usize) -> String;
+
+/// Every generator, keyed by the directory of the day it writes input for.
+pub const GENERATORS: &[(&str, Generator)] = &[
+    ("2022/day05", crate_moves),
+    ("2022/day09", rope_movements),
+    ("2023/day05", almanac),
+    ("2023/day06", races),
+    ("2023/day07", camel_cards),
+    ("2023/day08", ghost_network),
+    ("2023/day09", histories),
+];
+
+pub fn generator(day: &str) -> Option<Generator> {
+    let day = day.trim_end_matches('/');
+    GENERATORS
+        .iter()
+        .find(|(name, _)| *name == day)
+        .map(|(_, generate)| *generate)
+}
+
+/// 2022 day 5: a drawing of 2 to 12 stacks and `size` moves, each of which only
+/// takes crates that are there.
+pub fn crate_moves(rng: &mut Rng, size: usize) -> String {
+    let mut stacks = (0..rng.between(2, 12))
+        .map(|_| {
+            (0..rng.between(0, 8))
+                .map(|_| (b'A' + rng.below(26) as u8) as char)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    if stacks.iter().all(|s| s.is_empty()) {
+        stacks[0].push('A');
+    }
+    let labels = (1..=stacks.len())
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>();
+    let width = labels.last().unwrap().len().max(3);
+    let cell = |s: &str| format!("{:^width$}", s, width = width);
+    let height = stacks.iter().map(|s| s.len()).max().unwrap();
+    let mut out = String::new();
+    for level in (0..height).rev() {
+        let row = stacks
+            .iter()
+            .map(|s| s.get(level).map_or(cell(""), |c| cell(&format!("[{}]", c))))
+            .collect::<Vec<_>>();
+        writeln!(out, "{}", row.join(" ")).unwrap();
+    }
+    let label_row = labels.iter().map(|l| cell(l)).collect::<Vec<_>>();
+    write!(out, "{}\n\n", label_row.join(" ")).unwrap();
+    for _ in 0..size {
+        let filled = (0..stacks.len())
+            .filter(|i| !stacks[*i].is_empty())
+            .collect::<Vec<_>>();
+        let from = *rng.pick(&filled);
+        let to = (from + rng.between(1, stacks.len() as i64 - 1) as usize) % stacks.len();
+        let has = stacks[from].len();
+        let count = rng.between(1, has as i64) as usize;
+        let moved = stacks[from].split_off(has - count);
+        stacks[to].extend(moved);
+        writeln!(
+            out,
+            "move {} from {} to {}",
+            count, labels[from], labels[to]
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// 2022 day 9: `size` head movements of 1 to 20 steps.
+pub fn rope_movements(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..size {
+        writeln!(
+            out,
+            "{} {}",
+            rng.pick(&["U", "D", "L", "R"]),
+            rng.between(1, 20)
+        )
+        .unwrap();
+    }
+    out
+}
+
+const ALMANAC_CATEGORIES: [&str; 8] = [
+    "seed",
+    "soil",
+    "fertilizer",
+    "water",
+    "light",
+    "temperature",
+    "humidity",
+    "location",
+];
+
+/// 2023 day 5: `size` seed ranges and maps of up to `size + 1` entries. Each map cuts
+/// the numbers below a limit into consecutive segments and lays them out again in a
+/// shuffled order, so no two entries overlap on either side; numbers past the limit
+/// map to themselves.
+pub fn almanac(rng: &mut Rng, size: usize) -> String {
+    let limit = 1u64 << rng.between(8, 32);
+    let seeds = (0..size.max(1))
+        .flat_map(|_| {
+            let start = rng.below(limit);
+            [start, 1 + rng.below((limit - start).min(limit / 8 + 1))]
+        })
+        .map(|n| n.to_string())
+        .collect::<Vec<_>>();
+    let mut out = format!("seeds: {}\n", seeds.join(" "));
+    for pair in ALMANAC_CATEGORIES.windows(2) {
+        let mut cuts = (0..rng.below(size as u64 + 1))
+            .map(|_| rng.below(limit))
+            .chain([0, limit])
+            .collect::<Vec<_>>();
+        cuts.sort();
+        cuts.dedup();
+        let mut segments = cuts
+            .windows(2)
+            .map(|w| (w[0], w[1] - w[0]))
+            .collect::<Vec<_>>();
+        rng.shuffle(&mut segments);
+        write!(out, "\n{}-to-{} map:\n", pair[0], pair[1]).unwrap();
+        let mut destination = 0;
+        for (source, length) in segments {
+            writeln!(out, "{} {} {}", destination, source, length).unwrap();
+            destination += length;
+        }
+    }
+    out
+}
+
+/// 2023 day 6: `size` races of up to 99 ms. Records are drawn up to one past the best
+/// possible distance, so some races can't be won and some only just. The input has no
+/// trailing newline, like the puzzle's.
+pub fn races(rng: &mut Rng, size: usize) -> String {
+    let (times, distances): (Vec<_>, Vec<_>) = (0..size.max(1))
+        .map(|_| {
+            let time = rng.between(0, 99);
+            let peak = time / 2 * (time - time / 2);
+            (time.to_string(), rng.between(0, peak + 1).to_string())
+        })
+        .unzip();
+    let width = distances.iter().map(|d| d.len()).max().unwrap();
+    let row = |values: &[String]| {
+        values
+            .iter()
+            .map(|v| format!("{:>width$}", v, width = width))
+            .collect::<Vec<_>>()
+            .join(" ")
+    };
+    format!("Time:     {}\nDistance: {}", row(&times), row(&distances))
+}
+
+/// 2023 day 7: `size` different hands of five cards, each with a bid.
+pub fn camel_cards(rng: &mut Rng, size: usize) -> String {
+    let cards = b"AKQJT98765432";
+    let mut seen = HashSet::<String>::new();
+    let mut out = String::new();
+    while seen.len() < size {
+        // Drawing from a few ranks at a time makes pairs and full houses common.
+        let ranks = (0..rng.between(1, 5))
+            .map(|_| *rng.pick(cards))
+            .collect::<Vec<_>>();
+        let hand = (0..5)
+            .map(|_| *rng.pick(&ranks) as char)
+            .collect::<String>();
+        if seen.insert(hand.clone()) {
+            writeln!(out, "{} {}", hand, rng.between(1, 1000)).unwrap();
+        }
+    }
+    out
+}
+
+/// 2023 day 8: a network of `size` nodes, at least two, with random turns and 1 to 8
+/// instructions. About a fifth of the nodes end in `A` and another fifth in `Z`; there
+/// is always a start, but the ghosts need not ever line up on ends.
+pub fn ghost_network(rng: &mut Rng, size: usize) -> String {
+    const DIGITS: &[u8] = b"0123456789BCDEFGHIJKLMNOPQRSTUVWXY";
+    let count = size.clamp(2, DIGITS.len() * DIGITS.len());
+    let names = (0..count)
+        .map(|i| {
+            let suffix = match (i, rng.below(5)) {
+                (0, _) | (_, 0) => 'A',
+                (_, 1) => 'Z',
+                _ => 'X',
+            };
+            let (high, low) = (i / DIGITS.len(), i % DIGITS.len());
+            format!("{}{}{}", DIGITS[high] as char, DIGITS[low] as char, suffix)
+        })
+        .collect::<Vec<_>>();
+    let instructions = (0..rng.between(1, 8))
+        .map(|_| *rng.pick(&['L', 'R']))
+        .collect::<String>();
+    let mut out = format!("{}\n\n", instructions);
+    for name in &names {
+        writeln!(
+            out,
+            "{} = ({}, {})",
+            name,
+            rng.pick(&names),
+            rng.pick(&names)
+        )
+        .unwrap();
+    }
+    out
+}
+
+/// 2023 day 9: `size` histories of 3 to 21 values, each a polynomial of degree at most
+/// six, so the differences always reach zero before the values run out.
+pub fn histories(rng: &mut Rng, size: usize) -> String {
+    let mut out = String::new();
+    for _ in 0..size {
+        let len = rng.between(3, 21) as usize;
+        let degree = rng.between(0, (len as i64 - 2).min(6)) as usize;
+        // The first value of each row of differences; adding every row to the one
+        // above it steps the whole table one value to the right.
+        let mut firsts = (0..=degree)
+            .map(|row| rng.between(-10, 10) * if row == 0 { 100 } else { 1 })
+            .collect::<Vec<_>>();
+        let mut values = vec![];
+        for _ in 0..len {
+            values.push(firsts[0].to_string());
+            for row in 0..degree {
+                firsts[row] += firsts[row + 1];
+            }
+        }
+        writeln!(out, "{}", values.join(" ")).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rng_is_deterministic() {
+        let draw = |seed| {
+            let mut rng = Rng::new(seed);
+            (0..5).map(|_| rng.below(100)).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert_ne!(draw(7), draw(8));
+        let mut rng = Rng::new(1);
+        assert!((0..1000).all(|_| (-3..=3).contains(&rng.between(-3, 3))));
+    }
+
+    #[test]
+    fn test_generators_are_deterministic() {
+        for (name, generate) in GENERATORS {
+            let first = generate(&mut Rng::new(42), 20);
+            assert!(!first.is_empty(), "{} wrote nothing", name);
+            assert_eq!(first, generate(&mut Rng::new(42), 20), "{}", name);
+        }
+        assert!(generator("2023/day07/").is_some());
+        assert!(generator("2023/day25").is_none());
+    }
+
+    #[test]
+    fn test_almanac_maps_are_bijections() {
+        for seed in 0..50 {
+            let data = almanac(&mut Rng::new(seed), 10);
+            for block in data.split("\n\n").skip(1) {
+                let entries = block
+                    .lines()
+                    .skip(1)
+                    .map(|l| {
+                        let n = l
+                            .split(' ')
+                            .map(|n| n.parse::<u64>().unwrap())
+                            .collect::<Vec<_>>();
+                        (n[0], n[1], n[2])
+                    })
+                    .collect::<Vec<_>>();
+                let covered = |side: fn(&(u64, u64, u64)) -> u64| {
+                    let mut ranges = entries.iter().map(|e| (side(e), e.2)).collect::<Vec<_>>();
+                    ranges.sort();
+                    ranges.windows(2).all(|w| w[0].0 + w[0].1 == w[1].0) && ranges[0].0 == 0
+                };
+                assert!(
+                    covered(|e| e.0),
+                    "seed {}: destinations overlap or gap",
+                    seed
+                );
+                assert!(covered(|e| e.1), "seed {}: sources overlap or gap", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn test_crate_moves_keep_enough_crates() {
+        let data = crate_moves(&mut Rng::new(3), 30);
+        let (drawing, moves) = data.split_once("\n\n").unwrap();
+        assert!(drawing
+            .lines()
+            .last()
+            .unwrap()
+            .trim_start()
+            .starts_with('1'));
+        assert_eq!(moves.lines().count(), 30);
+        assert!(moves.lines().all(|l| l.starts_with("move ")));
+    }
+
+    #[test]
+    fn test_histories_are_polynomials() {
+        for line in histories(&mut Rng::new(5), 50).lines() {
+            let mut row = line
+                .split(' ')
+                .map(|n| n.parse::<i64>().unwrap())
+                .collect::<Vec<_>>();
+            while row.iter().any(|n| *n != 0) {
+                assert!(row.len() > 1, "{} never reaches zero", line);
+                row = row.windows(2).map(|w| w[1] - w[0]).collect();
+            }
+        }
+    }
+}
@@ -0,0 +1,392 @@
+//! Renders grids of palette colours to PNG images and animated GIFs without any
+//! dependencies.
+//!
+//! Both encoders favour simplicity over size: PNG pixel data goes into uncompressed
+//! deflate blocks and GIF frames use plain LZW, which is plenty for puzzle-sized
+//! grids scaled up a few times.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+pub type Rgb = [u8; 3];
+
+/// A grid of palette indices, row by row from the top left.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl Frame {
+    pub fn new(width: usize, height: usize) -> Self {
+        Frame {
+            width,
+            height,
+            pixels: vec![0; width * height],
+        }
+    }
+
+    pub fn from_fn(width: usize, height: usize, colour: impl Fn(usize, usize) -> u8) -> Self {
+        Frame {
+            width,
+            height,
+            pixels: (0..height)
+                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                .map(|(x, y)| colour(x, y))
+                .collect(),
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, colour: u8) {
+        self.pixels[y * self.width + x] = colour;
+    }
+
+    /// Every cell becomes a `factor` by `factor` block.
+    pub fn scaled(&self, factor: usize) -> Frame {
+        Frame::from_fn(self.width * factor, self.height * factor, |x, y| {
+            self.get(x / factor, y / factor)
+        })
+    }
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let table = (0..256u32)
+        .map(|n| {
+            (0..8).fold(n, |c, _| {
+                if c & 1 == 1 {
+                    0xedb88320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                }
+            })
+        })
+        .collect::<Vec<_>>();
+    !bytes.iter().fold(!0u32, |c, b| {
+        table[((c ^ *b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
+
+fn adler32(bytes: &[u8]) -> u32 {
+    let (a, b) = bytes.iter().fold((1u32, 0u32), |(a, b), byte| {
+        let a = (a + *byte as u32) % 65521;
+        (a, (b + a) % 65521)
+    });
+    b << 16 | a
+}
+
+/// A zlib stream made of stored deflate blocks.
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let mut out = vec![0x78, 0x01];
+    let blocks = data.chunks(u16::MAX as usize).collect::<Vec<_>>();
+    if blocks.is_empty() {
+        out.extend([1, 0, 0, 0xff, 0xff]);
+    }
+    for (i, block) in blocks.iter().enumerate() {
+        let len = block.len() as u16;
+        out.push((i + 1 == blocks.len()) as u8);
+        out.extend(len.to_le_bytes());
+        out.extend((!len).to_le_bytes());
+        out.extend_from_slice(block);
+    }
+    out.extend(adler32(data).to_be_bytes());
+    out
+}
+
+fn png_chunk(out: &mut Vec<u8>, kind: &[u8; 4], data: &[u8]) {
+    out.extend((data.len() as u32).to_be_bytes());
+    let start = out.len();
+    out.extend_from_slice(kind);
+    out.extend_from_slice(data);
+    let crc = crc32(&out[start..]);
+    out.extend(crc.to_be_bytes());
+}
+
+/// An 8-bit indexed colour PNG of `frame`.
+pub fn encode_png(frame: &Frame, palette: &[Rgb]) -> Vec<u8> {
+    assert!(
+        palette.len() <= 256,
+        "PNG palettes hold at most 256 colours"
+    );
+    let mut out = vec![0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'];
+    let mut header = vec![];
+    header.extend((frame.width as u32).to_be_bytes());
+    header.extend((frame.height as u32).to_be_bytes());
+    header.extend([8, 3, 0, 0, 0]);
+    png_chunk(&mut out, b"IHDR", &header);
+    png_chunk(&mut out, b"PLTE", &palette.concat());
+    let scanlines = frame
+        .pixels
+        .chunks(frame.width.max(1))
+        .flat_map(|row| std::iter::once(0).chain(row.iter().copied()))
+        .collect::<Vec<_>>();
+    png_chunk(&mut out, b"IDAT", &zlib_stored(&scanlines));
+    png_chunk(&mut out, b"IEND", &[]);
+    out
+}
+
+/// Packs variable width codes least significant bit first, as GIF expects.
+struct BitWriter {
+    bytes: Vec<u8>,
+    buffer: u32,
+    bits: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, code: u16, width: u32) {
+        self.buffer |= (code as u32) << self.bits;
+        self.bits += width;
+        while self.bits >= 8 {
+            self.bytes.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.bits -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.bits > 0 {
+            self.bytes.push(self.buffer as u8);
+        }
+        self.bytes
+    }
+}
+
+/// GIF flavoured LZW: codes start one bit wider than `min_code_size` and grow up to
+/// 12 bits, after which the table is cleared and rebuilt.
+fn lzw_encode(indices: &[u8], min_code_size: u32) -> Vec<u8> {
+    let clear = 1u16 << min_code_size;
+    let end = clear + 1;
+    let mut writer = BitWriter {
+        bytes: vec![],
+        buffer: 0,
+        bits: 0,
+    };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut next = end + 1;
+    let mut width = min_code_size + 1;
+    writer.write(clear, width);
+    let mut prefix: Option<u16> = None;
+    for byte in indices {
+        let Some(p) = prefix else {
+            prefix = Some(*byte as u16);
+            continue;
+        };
+        if let Some(code) = table.get(&(p, *byte)) {
+            prefix = Some(*code);
+            continue;
+        }
+        writer.write(p, width);
+        if next < 4096 {
+            table.insert((p, *byte), next);
+            next += 1;
+            if next > 1 << width && width < 12 {
+                width += 1;
+            }
+        } else {
+            writer.write(clear, width);
+            table.clear();
+            next = end + 1;
+            width = min_code_size + 1;
+        }
+        prefix = Some(*byte as u16);
+    }
+    if let Some(p) = prefix {
+        writer.write(p, width);
+    }
+    writer.write(end, width);
+    writer.finish()
+}
+
+/// An animated GIF that loops forever, showing each frame for `delay` hundredths of
+/// a second. Every frame must be the size of the first.
+pub fn encode_gif(frames: &[Frame], palette: &[Rgb], delay: u16) -> Vec<u8> {
+    assert!(!frames.is_empty(), "a GIF needs at least one frame");
+    assert!(
+        palette.len() <= 256,
+        "GIF palettes hold at most 256 colours"
+    );
+    let (width, height) = (frames[0].width as u16, frames[0].height as u16);
+    let table_bits = (1..=8).find(|b| 1 << b >= palette.len()).unwrap();
+    let mut out = b"GIF89a".to_vec();
+    out.extend(width.to_le_bytes());
+    out.extend(height.to_le_bytes());
+    out.extend([0x80 | (table_bits as u8 - 1), 0, 0]);
+    for i in 0..1 << table_bits {
+        out.extend(palette.get(i).unwrap_or(&[0, 0, 0]));
+    }
+    out.extend(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00");
+    let min_code_size = table_bits.max(2);
+    for frame in frames {
+        assert_eq!(
+            (frame.width, frame.height),
+            (width as usize, height as usize),
+            "every GIF frame must be the same size"
+        );
+        out.extend([0x21, 0xf9, 4, 0]);
+        out.extend(delay.to_le_bytes());
+        out.extend([0, 0, 0x2c, 0, 0, 0, 0]);
+        out.extend(width.to_le_bytes());
+        out.extend(height.to_le_bytes());
+        out.push(0);
+        out.push(min_code_size as u8);
+        for block in lzw_encode(&frame.pixels, min_code_size).chunks(255) {
+            out.push(block.len() as u8);
+            out.extend_from_slice(block);
+        }
+        out.push(0);
+    }
+    out.push(0x3b);
+    out
+}
+
+/// Writes `frames` to `path`: an animation for `.gif`, the last frame for `.png`.
+pub fn save(path: &Path, frames: &[Frame], palette: &[Rgb], delay: u16) -> io::Result<()> {
+    let extension = path.extension().and_then(|e| e.to_str());
+    let bytes = match (extension, frames.last()) {
+        (_, None) => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no frames to render",
+            ))
+        }
+        (Some("gif"), _) => encode_gif(frames, palette, delay),
+        (Some("png"), Some(last)) => encode_png(last, palette),
+        _ => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot render to {}, use .gif or .png", path.display()),
+            ))
+        }
+    };
+    fs::write(path, bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A straightforward GIF LZW decoder, to check the encoder against.
+    fn lzw_decode(bytes: &[u8], min_code_size: u32) -> Vec<u8> {
+        let clear = 1usize << min_code_size;
+        let mut table: Vec<Vec<u8>> = vec![];
+        let mut width = min_code_size + 1;
+        let (mut buffer, mut bits, mut pos) = (0u32, 0u32, 0);
+        let mut previous: Option<usize> = None;
+        let mut out = vec![];
+        loop {
+            while bits < width {
+                buffer |= (bytes[pos] as u32) << bits;
+                pos += 1;
+                bits += 8;
+            }
+            let code = (buffer & ((1 << width) - 1)) as usize;
+            buffer >>= width;
+            bits -= width;
+            if code == clear {
+                table = (0..clear).map(|i| vec![i as u8]).collect();
+                table.extend([vec![], vec![]]);
+                width = min_code_size + 1;
+                previous = None;
+                continue;
+            }
+            if code == clear + 1 {
+                return out;
+            }
+            let entry = match previous {
+                None => table[code].clone(),
+                Some(p) => {
+                    let entry = if code < table.len() {
+                        table[code].clone()
+                    } else {
+                        let mut e = table[p].clone();
+                        e.push(table[p][0]);
+                        e
+                    };
+                    if table.len() < 4096 {
+                        let mut added = table[p].clone();
+                        added.push(entry[0]);
+                        table.push(added);
+                    }
+                    entry
+                }
+            };
+            if table.len() == 1 << width && width < 12 {
+                width += 1;
+            }
+            out.extend(&entry);
+            previous = Some(code);
+        }
+    }
+
+    #[test]
+    fn test_lzw_round_trip() {
+        let mut seed = 7u32;
+        let noise = (0..20_000)
+            .map(|_| {
+                seed = seed.wrapping_mul(1103515245).wrapping_add(12345);
+                (seed >> 16) as u8 % 5
+            })
+            .collect::<Vec<_>>();
+        let long_run = vec![1u8; 100_000];
+        for (data, bits) in [
+            (noise, 3),
+            (long_run, 2),
+            (vec![0, 1, 0, 1], 2),
+            (vec![], 2),
+        ] {
+            assert_eq!(lzw_decode(&lzw_encode(&data, bits), bits), data);
+        }
+    }
+
+    #[test]
+    fn test_png_layout() {
+        let frame = Frame::from_fn(3, 2, |x, y| (x + y) as u8 % 2);
+        let png = encode_png(&frame, &[[0, 0, 0], [255, 255, 255]]);
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(&png[12..16], b"IHDR");
+        assert_eq!(&png[16..24], &[0, 0, 0, 3, 0, 0, 0, 2]);
+        assert_eq!(&png[png.len() - 8..png.len() - 4], b"IEND");
+        // The well known CRC of an empty IEND chunk.
+        assert_eq!(&png[png.len() - 4..], &[0xae, 0x42, 0x60, 0x82]);
+        let idat = png.windows(4).position(|w| w == b"IDAT").unwrap() + 4;
+        // Stored block header, then one filter byte and three pixels per row.
+        assert_eq!(&png[idat + 2..idat + 7], &[1, 8, 0, 0xf7, 0xff]);
+        assert_eq!(&png[idat + 7..idat + 15], &[0, 0, 1, 0, 0, 1, 0, 1]);
+    }
+
+    #[test]
+    fn test_gif_layout() {
+        let frames = [Frame::new(4, 4), Frame::from_fn(4, 4, |x, _| x as u8)];
+        let palette = [[0, 0, 0], [255, 0, 0], [0, 255, 0], [0, 0, 255], [9, 9, 9]];
+        let gif = encode_gif(&frames, &palette, 10);
+        assert_eq!(&gif[..6], b"GIF89a");
+        // Five colours need an eight entry table.
+        assert_eq!(gif[10], 0x80 | 2);
+        let mut pos = 13 + 8 * 3 + 19;
+        for frame in &frames {
+            assert_eq!(&gif[pos..pos + 4], &[0x21, 0xf9, 4, 0]);
+            assert_eq!(&gif[pos + 4..pos + 6], &10u16.to_le_bytes());
+            pos += 8;
+            assert_eq!(gif[pos], 0x2c);
+            let min_code_size = gif[pos + 10] as u32;
+            pos += 11;
+            let mut data = vec![];
+            while gif[pos] != 0 {
+                let len = gif[pos] as usize;
+                data.extend_from_slice(&gif[pos + 1..pos + 1 + len]);
+                pos += 1 + len;
+            }
+            pos += 1;
+            assert_eq!(lzw_decode(&data, min_code_size), frame.pixels);
+        }
+        assert_eq!(pos, gif.len() - 1);
+        assert_eq!(*gif.last().unwrap(), 0x3b);
+        assert_eq!(Frame::new(2, 1).scaled(2), Frame::new(4, 2));
+    }
+}
@@ -0,0 +1,9 @@
+pub mod alloc;
+pub mod check;
+pub mod fuzz;
+pub mod gen;
+pub mod image;
+pub mod ocr;
+pub mod report;
+pub mod runner;
+pub mod sink;
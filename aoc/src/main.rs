@@ -0,0 +1,111 @@
+//! `aoc gen <day> [--size N] [--seed S]` prints a random input for `day`, for example
+//! `2023/day07`, and `aoc gen --list` lists the days that have a generator.
+//!
+//! `aoc run [--jobs N] [--timeout SECS] [--toolchain NAME] [--alloc-budget SIZE]
+//! [--root DIR] [FILTER...]` builds and runs every day concurrently and prints each
+//! part's answer with its time and memory, then the failed parts and a summary per
+//! year. Filters such as `2023` or `2022/day05` pick which days to run.
+
+use std::path::{Path, PathBuf};
+use std::process;
+use std::time::Duration;
+
+use aoc::gen::{self, Rng};
+use aoc::report;
+use aoc::runner::{self, Options};
+
+fn usage() -> ! {
+    eprintln!("usage: aoc gen <day> [--size N] [--seed S]");
+    eprintln!("       aoc gen --list");
+    eprintln!(
+        "       aoc run [--jobs N] [--timeout SECS] [--toolchain NAME] [--alloc-budget SIZE]"
+    );
+    eprintln!("               [--root DIR] [FILTER...]");
+    process::exit(2);
+}
+
+fn value<T: std::str::FromStr>(args: &mut impl Iterator<Item = String>) -> T {
+    args.next()
+        .and_then(|s| s.parse().ok())
+        .unwrap_or_else(|| usage())
+}
+
+fn generate(mut args: impl Iterator<Item = String>) {
+    let mut day = None;
+    let mut size = 10;
+    let mut seed = 0;
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--list" => {
+                for (name, _) in gen::GENERATORS {
+                    println!("{}", name);
+                }
+                return;
+            }
+            "--size" => size = value(&mut args),
+            "--seed" => seed = value(&mut args),
+            _ if day.is_none() && !arg.starts_with("--") => day = Some(arg),
+            _ => usage(),
+        }
+    }
+    let day = day.unwrap_or_else(|| usage());
+    let Some(generate) = gen::generator(&day) else {
+        eprintln!("No generator for {}; try aoc gen --list", day);
+        process::exit(1);
+    };
+    print!("{}", generate(&mut Rng::new(seed), size));
+}
+
+fn run(mut args: impl Iterator<Item = String>) {
+    let mut options = Options::default();
+    let mut root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+    let mut filters = vec![];
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--jobs" => options.jobs = value(&mut args),
+            "--timeout" => {
+                let seconds: f64 = value(&mut args);
+                options.timeout = Duration::try_from_secs_f64(seconds).unwrap_or_else(|_| usage());
+            }
+            "--toolchain" => {
+                let toolchain: String = value(&mut args);
+                options.toolchain = Some(toolchain).filter(|t| !t.is_empty());
+            }
+            "--alloc-budget" => {
+                let size: String = value(&mut args);
+                options.alloc_budget = Some(report::parse_size(&size).unwrap_or_else(|| usage()));
+            }
+            "--root" => root = value::<PathBuf>(&mut args),
+            _ if !arg.starts_with("--") => filters.push(arg.trim_end_matches('/').to_string()),
+            _ => usage(),
+        }
+    }
+    let solvers = runner::discover(&root)
+        .into_iter()
+        .filter(|s| {
+            let name = s.name();
+            filters.is_empty()
+                || filters
+                    .iter()
+                    .any(|f| name == *f || name.starts_with(&format!("{}/", f)))
+        })
+        .collect::<Vec<_>>();
+    if solvers.is_empty() {
+        eprintln!("No days to run under {}", root.display());
+        process::exit(1);
+    }
+    let runs = runner::run_all(&solvers, &options, |run| {
+        println!("{}", runner::format_run(run))
+    });
+    println!();
+    println!("{}", runner::summary(&runs));
+}
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("gen") => generate(args),
+        Some("run") => run(args),
+        _ => usage(),
+    }
+}
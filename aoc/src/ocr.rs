@@ -0,0 +1,203 @@
+//! Reads the block letters that puzzles draw on a grid of lit and dark cells.
+//!
+//! Two fonts are known: the 6 row font used by most puzzles (letters about 4 cells
+//! wide) and the larger 10 row one (about 6 cells wide). Letters are separated by at
+//! least one fully dark column, so the grid is split on those columns rather than
+//! on a fixed stride.
+
+use std::fmt;
+
+const FONT_6: [(&str, char); 18] = [
+    (".##.\n#..#\n#..#\n####\n#..#\n#..#", 'A'),
+    ("###.\n#..#\n###.\n#..#\n#..#\n###.", 'B'),
+    (".##.\n#..#\n#...\n#...\n#..#\n.##.", 'C'),
+    ("####\n#...\n###.\n#...\n#...\n####", 'E'),
+    ("####\n#...\n###.\n#...\n#...\n#...", 'F'),
+    (".##.\n#..#\n#...\n#.##\n#..#\n.###", 'G'),
+    ("#..#\n#..#\n####\n#..#\n#..#\n#..#", 'H'),
+    (".###\n..#.\n..#.\n..#.\n..#.\n.###", 'I'),
+    ("..##\n...#\n...#\n...#\n#..#\n.##.", 'J'),
+    ("#..#\n#.#.\n##..\n#.#.\n#.#.\n#..#", 'K'),
+    ("#...\n#...\n#...\n#...\n#...\n####", 'L'),
+    (".##.\n#..#\n#..#\n#..#\n#..#\n.##.", 'O'),
+    ("###.\n#..#\n#..#\n###.\n#...\n#...", 'P'),
+    ("###.\n#..#\n#..#\n###.\n#.#.\n#..#", 'R'),
+    (".###\n#...\n#...\n.##.\n...#\n###.", 'S'),
+    ("#..#\n#..#\n#..#\n#..#\n#..#\n.##.", 'U'),
+    ("#...#\n#...#\n.#.#.\n..#..\n..#..\n..#..", 'Y'),
+    ("####\n...#\n..#.\n.#..\n#...\n####", 'Z'),
+];
+
+#[rustfmt::skip]
+const FONT_10: [(&str, char); 15] = [
+    ("..##..\n.#..#.\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#", 'A'),
+    ("#####.\n#....#\n#....#\n#....#\n#####.\n#....#\n#....#\n#....#\n#....#\n#####.", 'B'),
+    (".####.\n#....#\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#....#\n.####.", 'C'),
+    ("######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n######", 'E'),
+    ("######\n#.....\n#.....\n#.....\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....", 'F'),
+    (".####.\n#....#\n#.....\n#.....\n#.....\n#..###\n#....#\n#....#\n#...##\n.###.#", 'G'),
+    ("#....#\n#....#\n#....#\n#....#\n######\n#....#\n#....#\n#....#\n#....#\n#....#", 'H'),
+    ("...###\n....#.\n....#.\n....#.\n....#.\n....#.\n....#.\n#...#.\n#...#.\n.###..", 'J'),
+    ("#....#\n#...#.\n#..#..\n#.#...\n##....\n##....\n#.#...\n#..#..\n#...#.\n#....#", 'K'),
+    ("#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n#.....\n######", 'L'),
+    ("#....#\n##...#\n##...#\n#.#..#\n#.#..#\n#..#.#\n#..#.#\n#...##\n#...##\n#....#", 'N'),
+    ("#####.\n#....#\n#....#\n#....#\n#####.\n#.....\n#.....\n#.....\n#.....\n#.....", 'P'),
+    ("#####.\n#....#\n#....#\n#....#\n#####.\n#..#..\n#...#.\n#...#.\n#....#\n#....#", 'R'),
+    ("#....#\n#....#\n.#..#.\n.#..#.\n..##..\n..##..\n.#..#.\n.#..#.\n#....#\n#....#", 'X'),
+    ("######\n.....#\n.....#\n....#.\n...#..\n..#...\n.#....\n#.....\n#.....\n######", 'Z'),
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum OcrError {
+    /// Only grids 6 or 10 cells high are known.
+    UnsupportedHeight(usize),
+    RaggedRows,
+    /// A glyph starting at `column` matches no letter; `glyph` is its `#`/`.` drawing.
+    UnknownGlyph {
+        column: usize,
+        glyph: String,
+    },
+}
+
+impl fmt::Display for OcrError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            OcrError::UnsupportedHeight(h) => write!(f, "no font is {} rows high", h),
+            OcrError::RaggedRows => write!(f, "grid rows differ in length"),
+            OcrError::UnknownGlyph { column, glyph } => {
+                write!(f, "unknown glyph at column {}:\n{}", column, glyph)
+            }
+        }
+    }
+}
+
+impl std::error::Error for OcrError {}
+
+/// Columns of a glyph as bitmasks, with blank columns on either side removed.
+fn glyph_columns(rows: &[Vec<bool>], columns: std::ops::Range<usize>) -> Vec<u16> {
+    let mut cols = columns
+        .map(|c| {
+            rows.iter()
+                .enumerate()
+                .fold(0u16, |mask, (r, row)| mask | (row[c] as u16) << r)
+        })
+        .collect::<Vec<_>>();
+    while cols.last() == Some(&0) {
+        cols.pop();
+    }
+    let leading = cols.iter().take_while(|c| **c == 0).count();
+    cols.split_off(leading)
+}
+
+fn font(height: usize) -> Result<Vec<(Vec<u16>, char)>, OcrError> {
+    let glyphs: &[(&str, char)] = match height {
+        6 => &FONT_6,
+        10 => &FONT_10,
+        h => return Err(OcrError::UnsupportedHeight(h)),
+    };
+    Ok(glyphs
+        .iter()
+        .map(|(drawing, letter)| {
+            let rows = drawing
+                .lines()
+                .map(|l| l.chars().map(|c| c == '#').collect())
+                .collect::<Vec<Vec<bool>>>();
+            (glyph_columns(&rows, 0..rows[0].len()), *letter)
+        })
+        .collect())
+}
+
+/// Decodes a grid given as rows of cells, where `is_lit` tells which cells are on.
+pub fn decode_with(grid: &str, is_lit: impl Fn(char) -> bool) -> Result<String, OcrError> {
+    let rows = grid
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .map(|l| {
+            l.trim_end_matches('\r')
+                .chars()
+                .map(&is_lit)
+                .collect::<Vec<_>>()
+        })
+        .collect::<Vec<_>>();
+    let font = font(rows.len())?;
+    let width = rows[0].len();
+    if rows.iter().any(|r| r.len() != width) {
+        return Err(OcrError::RaggedRows);
+    }
+    let blank = (0..width)
+        .map(|c| rows.iter().all(|r| !r[c]))
+        .chain([true])
+        .collect::<Vec<_>>();
+    let mut letters = String::new();
+    let mut start = None;
+    for (c, is_blank) in blank.iter().enumerate() {
+        match (start, is_blank) {
+            (None, false) => start = Some(c),
+            (Some(s), true) => {
+                let glyph = glyph_columns(&rows, s..c);
+                let letter = font
+                    .iter()
+                    .find(|(g, _)| *g == glyph)
+                    .map(|(_, l)| *l)
+                    .ok_or_else(|| OcrError::UnknownGlyph {
+                        column: s,
+                        glyph: rows
+                            .iter()
+                            .map(|r| r[s..c].iter().map(|l| if *l { '#' } else { '.' }).collect())
+                            .collect::<Vec<String>>()
+                            .join("\n"),
+                    })?;
+                letters.push(letter);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    Ok(letters)
+}
+
+/// Decodes a `#`/`.` drawing; any character other than `#` counts as dark.
+pub fn decode(grid: &str) -> Result<String, OcrError> {
+    decode_with(grid, |c| c == '#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_small_font() {
+        let grid = "####.#..#.####.####.####.#..#..##..####.
+#....#..#....#.#.......#.#..#.#..#....#.
+###..####...#..###....#..####.#......#..
+#....#..#..#...#.....#...#..#.#.....#...
+#....#..#.#....#....#....#..#.#..#.#....
+####.#..#.####.#....####.#..#..##..####.";
+        assert_eq!(decode(grid), Ok("EHZFZHCZ".to_string()));
+    }
+
+    #[test]
+    fn test_decode_large_font_with_custom_cells() {
+        let grid = FONT_10[10]
+            .0
+            .lines()
+            .zip(FONT_10[13].0.lines())
+            .map(|(n, x)| format!("{}  {}", n, x).replace('#', "█").replace('.', " "))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(decode_with(&grid, |c| c == '█'), Ok("NX".to_string()));
+    }
+
+    #[test]
+    fn test_unknown_glyph() {
+        let grid = "#.#\n.#.\n#.#\n.#.\n#.#\n.#.";
+        assert_eq!(
+            decode(grid),
+            Err(OcrError::UnknownGlyph {
+                column: 0,
+                glyph: grid.to_string()
+            })
+        );
+        assert_eq!(decode("#\n#"), Err(OcrError::UnsupportedHeight(2)));
+    }
+}
@@ -0,0 +1,166 @@
+//! Prints a day's answers, optionally with what each part cost.
+//!
+//! `--stats` adds a line after every answer with the time, allocations, bytes
+//! allocated and peak memory of that part. With `--alloc-budget <size>` a part whose
+//! peak memory went over `size` (bytes, or with a `K`, `M` or `G` suffix) fails the
+//! run once it finishes, and its answer is not printed.
+
+use std::fmt::Display;
+use std::process;
+
+use crate::alloc::{self, Usage};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Options {
+    pub stats: bool,
+    pub alloc_budget: Option<usize>,
+}
+
+/// Parses sizes such as `512`, `64K` or `1.5M`, in powers of 1024.
+pub fn parse_size(s: &str) -> Option<usize> {
+    let s = s.trim();
+    let (number, unit) = match s.char_indices().find(|(_, c)| c.is_ascii_alphabetic()) {
+        Some((i, _)) => s.split_at(i),
+        None => (s, ""),
+    };
+    let unit = match unit
+        .to_ascii_uppercase()
+        .trim_end_matches("IB")
+        .trim_end_matches('B')
+    {
+        "" => 1,
+        "K" => 1 << 10,
+        "M" => 1 << 20,
+        "G" => 1 << 30,
+        _ => return None,
+    };
+    let number = number.trim().parse::<f64>().ok().filter(|n| *n >= 0.0)?;
+    Some((number * unit as f64) as usize)
+}
+
+fn budget(size: Option<&str>) -> Result<usize, String> {
+    let size = size.ok_or("--alloc-budget needs a size")?;
+    parse_size(size).ok_or_else(|| format!("invalid --alloc-budget size: {}", size))
+}
+
+/// Picks out the report options, leaving any others to the day.
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> Result<Options, String> {
+    let mut options = Options::default();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        if arg == "--stats" {
+            options.stats = true;
+        } else if arg == "--alloc-budget" {
+            options.alloc_budget = Some(budget(args.next().as_deref())?);
+        } else if let Some(size) = arg.strip_prefix("--alloc-budget=") {
+            options.alloc_budget = Some(budget(Some(size))?);
+        }
+    }
+    Ok(options)
+}
+
+pub fn format_bytes(bytes: usize) -> String {
+    match bytes {
+        b if b < 1 << 10 => format!("{} B", b),
+        b if b < 1 << 20 => format!("{:.1} KiB", b as f64 / (1 << 10) as f64),
+        b if b < 1 << 30 => format!("{:.1} MiB", b as f64 / (1 << 20) as f64),
+        b => format!("{:.1} GiB", b as f64 / (1 << 30) as f64),
+    }
+}
+
+pub fn format_usage(usage: &Usage) -> String {
+    format!(
+        "{:.3?}, {} allocations, {} allocated, {} peak",
+        usage.elapsed,
+        usage.allocations,
+        format_bytes(usage.allocated_bytes),
+        format_bytes(usage.peak_bytes)
+    )
+}
+
+/// Solves part `n`, prints `Part n: <answer>` and acts on the command line options.
+pub fn part<T: Display>(n: u8, solve: impl FnOnce() -> T) {
+    let options = parse_args(std::env::args().skip(1)).unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        eprintln!("usage: [--stats] [--alloc-budget SIZE]");
+        process::exit(2);
+    });
+    let (answer, usage) = alloc::measure(solve);
+    if let Some(budget) = options.alloc_budget.filter(|b| usage.peak_bytes > *b) {
+        eprintln!(
+            "Part {} went over the allocation budget: {} peak, {} allowed",
+            n,
+            format_bytes(usage.peak_bytes),
+            format_bytes(budget)
+        );
+        process::exit(1);
+    }
+    println!("Part {}: {}", n, answer);
+    if options.stats {
+        println!("Part {} stats: {}", n, format_usage(&usage));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("512"), Some(512));
+        assert_eq!(parse_size("64K"), Some(64 << 10));
+        assert_eq!(parse_size("1.5M"), Some(3 << 19));
+        assert_eq!(parse_size("2GiB"), Some(2 << 30));
+        assert_eq!(parse_size("1mb"), Some(1 << 20));
+        assert_eq!(parse_size("12X"), None);
+        assert_eq!(parse_size("-1"), None);
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            parse_args(args("--stats --alloc-budget 10M")),
+            Ok(Options {
+                stats: true,
+                alloc_budget: Some(10 << 20)
+            })
+        );
+        assert_eq!(
+            parse_args(args("--render out.gif --alloc-budget=1K")).map(|o| o.alloc_budget),
+            Ok(Some(1024))
+        );
+        assert_eq!(parse_args(args("")), Ok(Options::default()));
+    }
+
+    #[test]
+    fn test_parse_args_rejects_bad_budget() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            parse_args(args("--alloc-budget 10Q")),
+            Err("invalid --alloc-budget size: 10Q".to_string())
+        );
+        assert_eq!(
+            parse_args(args("--alloc-budget=")),
+            Err("invalid --alloc-budget size: ".to_string())
+        );
+        assert_eq!(
+            parse_args(args("--stats --alloc-budget")),
+            Err("--alloc-budget needs a size".to_string())
+        );
+    }
+
+    #[test]
+    fn test_format_usage() {
+        let usage = Usage {
+            elapsed: std::time::Duration::from_micros(1500),
+            allocations: 3,
+            allocated_bytes: 3 << 19,
+            peak_bytes: 100,
+        };
+        assert_eq!(
+            format_usage(&usage),
+            "1.500ms, 3 allocations, 1.5 MiB allocated, 100 B peak"
+        );
+    }
+}
@@ -0,0 +1,586 @@
+//! Builds and runs every day's solver on a pool of worker threads.
+//!
+//! A solver is any `<year>/<day>/Cargo.toml` under the repository root. A worker builds
+//! the day with cargo, runs its binary and reads the `Part n: <answer>` lines that
+//! [`crate::report::part`] prints, along with the `Part n stats:` line that follows
+//! each of them. When a part takes longer than the timeout the process is killed and
+//! the part marked as timed out, so one slow day can't hold up the rest of the
+//! calendar.
+
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::fs;
+use std::io::{BufRead, BufReader, Read};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+
+/// Parts every day prints.
+pub const PARTS: usize = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Solver {
+    pub year: String,
+    pub day: String,
+    pub dir: PathBuf,
+}
+
+impl Solver {
+    pub fn name(&self) -> String {
+        format!("{}/{}", self.year, self.day)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Solved {
+        answer: String,
+        elapsed: Duration,
+        /// The time and memory the day measured for the part itself.
+        stats: Option<String>,
+    },
+    TimedOut,
+    /// An earlier part failed or timed out, so this one never started.
+    NotRun,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Run {
+    pub solver: Solver,
+    pub parts: Vec<Outcome>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Options {
+    pub jobs: usize,
+    pub timeout: Duration,
+    /// Passed to cargo as `+toolchain`; the days need nightly for `#![feature(test)]`.
+    pub toolchain: Option<String>,
+    /// Passed to each day as `--alloc-budget`.
+    pub alloc_budget: Option<usize>,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            jobs: thread::available_parallelism().map_or(4, |n| n.get()),
+            timeout: Duration::from_secs(30),
+            toolchain: Some("nightly".to_string()),
+            alloc_budget: None,
+        }
+    }
+}
+
+fn subdirs(dir: &Path) -> Vec<(String, PathBuf)> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return vec![];
+    };
+    entries
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().is_dir())
+        .filter_map(|e| Some((e.file_name().into_string().ok()?, e.path())))
+        .collect()
+}
+
+/// Every day under `root` that has a cargo manifest, ordered by year and day.
+pub fn discover(root: &Path) -> Vec<Solver> {
+    let mut solvers = subdirs(root)
+        .into_iter()
+        .filter(|(year, _)| year.len() == 4 && year.bytes().all(|b| b.is_ascii_digit()))
+        .flat_map(|(year, path)| {
+            subdirs(&path)
+                .into_iter()
+                .filter(|(_, dir)| dir.join("Cargo.toml").is_file())
+                .map(move |(day, dir)| Solver {
+                    year: year.clone(),
+                    day,
+                    dir,
+                })
+        })
+        .collect::<Vec<_>>();
+    solvers.sort();
+    solvers
+}
+
+/// The binary path in one of cargo's `--message-format=json` messages, if it has one.
+pub fn executable(message: &str) -> Option<&str> {
+    let key = "\"executable\":\"";
+    let start = message.find(key)? + key.len();
+    let len = message[start..].find('"')?;
+    Some(&message[start..start + len])
+}
+
+/// Builds `solver` into its own `target` directory. Days are built concurrently and
+/// several share a crate name, so a shared `CARGO_TARGET_DIR` or `build.target-dir`
+/// would let one day's binary overwrite another's.
+fn build(solver: &Solver, toolchain: Option<&str>) -> Result<PathBuf, String> {
+    let mut cargo = Command::new("cargo");
+    if let Some(toolchain) = toolchain {
+        cargo.arg(format!("+{}", toolchain));
+    }
+    let output = cargo
+        .args([
+            "build",
+            "--release",
+            "--quiet",
+            "--message-format=json-render-diagnostics",
+        ])
+        .arg("--target-dir")
+        .arg(solver.dir.join("target"))
+        .current_dir(&solver.dir)
+        .output()
+        .map_err(|e| format!("could not start cargo: {}", e))?;
+    if !output.status.success() {
+        return Err(build_failure(&String::from_utf8_lossy(&output.stderr)));
+    }
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(executable)
+        .next_back()
+        .map(PathBuf::from)
+        .ok_or_else(|| "build produced no binary".to_string())
+}
+
+/// Why a build failed, from cargo's rendered diagnostics: the first error with the
+/// location it points at, or else the last line cargo printed.
+fn build_failure(stderr: &str) -> String {
+    let mut lines = stderr.lines().map(str::trim).filter(|l| !l.is_empty());
+    let first_error = lines
+        .by_ref()
+        .find(|l| l.starts_with("error") && !l.starts_with("error: could not compile"));
+    let reason = match first_error {
+        Some(error) => match lines.next().and_then(|l| l.strip_prefix("--> ")) {
+            Some(location) => format!("{} at {}", error, location),
+            None => error.to_string(),
+        },
+        None => match stderr.lines().rev().map(str::trim).find(|l| !l.is_empty()) {
+            Some(last) => last.to_string(),
+            None => return "build failed".to_string(),
+        },
+    };
+    format!("build failed: {}", reason)
+}
+
+fn stop(child: &mut Child) {
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+/// Runs `command` and collects an outcome for each of the [`PARTS`] it should print.
+/// Each part gets `timeout` from the end of the one before it, and the process gets
+/// `timeout` more to exit once the last answer is in. A part that fails is reported
+/// with the last line the process wrote to stderr.
+pub fn run_parts(mut command: Command, timeout: Duration) -> Vec<Outcome> {
+    let spawned = command
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn();
+    let mut child = match spawned {
+        Ok(child) => child,
+        Err(e) => return vec![Outcome::Failed(format!("could not start: {}", e)); PARTS],
+    };
+    let stdout = child.stdout.take().unwrap();
+    let mut stderr = child.stderr.take().unwrap();
+    let errors = thread::spawn(move || {
+        let mut errors = String::new();
+        let _ = stderr.read_to_string(&mut errors);
+        errors
+    });
+    let (lines, received) = mpsc::channel();
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines().map_while(Result::ok) {
+            if lines.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    let mut parts = vec![];
+    let mut started = Instant::now();
+    loop {
+        match received.recv_timeout(timeout.saturating_sub(started.elapsed())) {
+            Ok(line) => {
+                let prefix = format!("Part {}: ", parts.len() + 1);
+                let stats_prefix = format!("Part {} stats: ", parts.len());
+                if let Some(answer) = line.strip_prefix(&prefix) {
+                    parts.push(Outcome::Solved {
+                        answer: answer.to_string(),
+                        elapsed: started.elapsed(),
+                        stats: None,
+                    });
+                    started = Instant::now();
+                } else if let Some(usage) = line.strip_prefix(&stats_prefix) {
+                    if let Some(Outcome::Solved { stats, .. }) = parts.last_mut() {
+                        *stats = Some(usage.to_string());
+                    }
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {
+                stop(&mut child);
+                if parts.len() < PARTS {
+                    parts.push(Outcome::TimedOut);
+                    parts.resize(PARTS, Outcome::NotRun);
+                }
+                return parts;
+            }
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+    }
+    let failure = match child.wait() {
+        Ok(status) if status.success() => "exited without an answer".to_string(),
+        Ok(status) => {
+            let errors = errors.join().unwrap_or_default();
+            match errors.lines().rev().find(|l| !l.trim().is_empty()) {
+                Some(error) => format!("exited with {}: {}", status, error.trim()),
+                None => format!("exited with {}", status),
+            }
+        }
+        Err(e) => e.to_string(),
+    };
+    if parts.len() < PARTS {
+        parts.push(Outcome::Failed(failure));
+        parts.resize(PARTS, Outcome::NotRun);
+    }
+    parts
+}
+
+pub fn run_solver(solver: &Solver, options: &Options) -> Run {
+    let parts = match build(solver, options.toolchain.as_deref()) {
+        Ok(binary) => {
+            let mut command = Command::new(binary);
+            command.current_dir(&solver.dir).arg("--stats");
+            if let Some(budget) = options.alloc_budget {
+                command.arg(format!("--alloc-budget={}", budget));
+            }
+            run_parts(command, options.timeout)
+        }
+        Err(e) => {
+            let mut parts = vec![Outcome::Failed(e)];
+            parts.resize(PARTS, Outcome::NotRun);
+            parts
+        }
+    };
+    Run {
+        solver: solver.clone(),
+        parts,
+    }
+}
+
+/// Runs every solver on `options.jobs` worker threads, calling `on_done` as each one
+/// finishes, and returns the runs in solver order.
+pub fn run_all(solvers: &[Solver], options: &Options, on_done: impl Fn(&Run) + Sync) -> Vec<Run> {
+    let next = AtomicUsize::new(0);
+    let runs = Mutex::new(vec![]);
+    thread::scope(|scope| {
+        for _ in 0..options.jobs.max(1) {
+            scope.spawn(|| {
+                while let Some(solver) = solvers.get(next.fetch_add(1, Ordering::Relaxed)) {
+                    let run = run_solver(solver, options);
+                    on_done(&run);
+                    runs.lock().unwrap().push(run);
+                }
+            });
+        }
+    });
+    let mut runs = runs.into_inner().unwrap();
+    runs.sort_by(|a, b| a.solver.cmp(&b.solver));
+    runs
+}
+
+pub fn format_outcome(outcome: &Outcome) -> String {
+    match outcome {
+        Outcome::Solved {
+            answer,
+            stats: Some(stats),
+            ..
+        } => format!("{} ({})", answer, stats),
+        Outcome::Solved {
+            answer, elapsed, ..
+        } => format!("{} ({:.3?})", answer, elapsed),
+        Outcome::TimedOut => "timed out".to_string(),
+        Outcome::NotRun => "not run".to_string(),
+        Outcome::Failed(reason) => format!("failed, {}", reason),
+    }
+}
+
+pub fn format_run(run: &Run) -> String {
+    let parts = run
+        .parts
+        .iter()
+        .enumerate()
+        .map(|(i, outcome)| format!("part {}: {}", i + 1, format_outcome(outcome)))
+        .collect::<Vec<_>>();
+    format!("{:<12} {}", run.solver.name(), parts.join("  "))
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct Totals {
+    solvers: usize,
+    solved: usize,
+    timed_out: usize,
+    failed: usize,
+    elapsed: Duration,
+}
+
+impl Totals {
+    fn add(&mut self, run: &Run) {
+        self.solvers += 1;
+        for outcome in &run.parts {
+            match outcome {
+                Outcome::Solved { elapsed, .. } => {
+                    self.solved += 1;
+                    self.elapsed += *elapsed;
+                }
+                Outcome::TimedOut => self.timed_out += 1,
+                Outcome::NotRun | Outcome::Failed(_) => self.failed += 1,
+            }
+        }
+    }
+
+    fn line(&self, label: &str) -> String {
+        format!(
+            "{:<6} {} solvers, {}/{} parts solved, {} timed out, {} failed, {:.3?} solving",
+            label,
+            self.solvers,
+            self.solved,
+            self.solvers * PARTS,
+            self.timed_out,
+            self.failed,
+            self.elapsed
+        )
+    }
+}
+
+/// Every failed part with its reason, then totals for each year and for the whole
+/// calendar. Parts that never ran count as failed, and only solved parts add to the
+/// time.
+pub fn summary(runs: &[Run]) -> String {
+    let mut years = BTreeMap::<&str, Totals>::new();
+    let mut total = Totals::default();
+    let mut out = String::new();
+    for run in runs {
+        years.entry(&run.solver.year).or_default().add(run);
+        total.add(run);
+        for (i, outcome) in run.parts.iter().enumerate() {
+            if let Outcome::Failed(_) = outcome {
+                let part = format!("part {}: {}", i + 1, format_outcome(outcome));
+                writeln!(out, "{:<12} {}", run.solver.name(), part).unwrap();
+            }
+        }
+    }
+    for (year, totals) in years {
+        writeln!(out, "{}", totals.line(year)).unwrap();
+    }
+    write!(out, "{}", total.line("total")).unwrap();
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sh(script: &str) -> Command {
+        let mut command = Command::new("sh");
+        command.args(["-c", script]);
+        command
+    }
+
+    fn solver(year: &str, day: &str) -> Solver {
+        Solver {
+            year: year.to_string(),
+            day: day.to_string(),
+            dir: PathBuf::new(),
+        }
+    }
+
+    #[test]
+    fn test_discover() {
+        let root = Path::new(env!("CARGO_MANIFEST_DIR")).join("..");
+        let names = discover(&root).iter().map(|s| s.name()).collect::<Vec<_>>();
+        assert!(names.contains(&"2023/day05".to_string()));
+        assert!(names.iter().all(|n| !n.starts_with("aoc")));
+        assert!(names.windows(2).all(|w| w[0] < w[1]));
+    }
+
+    #[test]
+    fn test_executable() {
+        let message =
+            r#"{"reason":"compiler-artifact","executable":"/x/target/release/day05","fresh":true}"#;
+        assert_eq!(executable(message), Some("/x/target/release/day05"));
+        assert_eq!(executable(r#"{"executable":null}"#), None);
+    }
+
+    #[test]
+    fn test_same_named_days_keep_their_binaries() {
+        let root = std::env::temp_dir().join(format!("aoc-runner-{}", std::process::id()));
+        let shared = root.join("shared-target");
+        fs::create_dir_all(root.join(".cargo")).unwrap();
+        fs::write(
+            root.join(".cargo/config.toml"),
+            format!("[build]\ntarget-dir = {:?}\n", shared),
+        )
+        .unwrap();
+        for year in ["2022", "2023", "2024"] {
+            let dir = root.join(year).join("day05");
+            fs::create_dir_all(dir.join("src")).unwrap();
+            fs::write(
+                dir.join("Cargo.toml"),
+                "[package]\nname = \"day05\"\nversion = \"0.1.0\"\nedition = \"2021\"\n\n[workspace]\n",
+            )
+            .unwrap();
+            fs::write(
+                dir.join("src/main.rs"),
+                match year {
+                    "2024" => "fn main() {\n    missing();\n}".to_string(),
+                    _ => format!(
+                        "fn main() {{ println!(\"Part 1: {0}\"); println!(\"Part 2: {0}\"); }}",
+                        year
+                    ),
+                },
+            )
+            .unwrap();
+        }
+        let options = Options {
+            jobs: 2,
+            timeout: Duration::from_secs(120),
+            toolchain: None,
+            alloc_budget: None,
+        };
+        let mut runs = run_all(&discover(&root), &options, |_| {});
+        fs::remove_dir_all(&root).unwrap();
+        let broken = runs.pop().unwrap();
+        assert_eq!(
+            broken.parts,
+            vec![
+                Outcome::Failed(
+                    "build failed: error[E0425]: cannot find function `missing` in this scope \
+                     at src/main.rs:2:5"
+                        .to_string()
+                ),
+                Outcome::NotRun
+            ]
+        );
+        for run in runs {
+            for part in &run.parts {
+                assert!(
+                    matches!(part, Outcome::Solved { answer, .. } if *answer == run.solver.year),
+                    "{}",
+                    format_run(&run)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_build_failure() {
+        let stderr = "error[E0425]: cannot find value `x` in this scope\n \
+                      --> src/main.rs:2:5\n  |\n2 |     x\n  |     ^ not found in this scope\n\n\
+                      error: could not compile `day05` (bin \"day05\") due to 1 previous error\n";
+        assert_eq!(
+            build_failure(stderr),
+            "build failed: error[E0425]: cannot find value `x` in this scope at src/main.rs:2:5"
+        );
+        assert_eq!(
+            build_failure("error: could not find `Cargo.toml`\n"),
+            "build failed: error: could not find `Cargo.toml`"
+        );
+        assert_eq!(
+            build_failure("warning: unused\nsomething broke\n"),
+            "build failed: something broke"
+        );
+        assert_eq!(build_failure(""), "build failed");
+    }
+
+    #[test]
+    fn test_run_parts_reads_answers() {
+        let parts = run_parts(
+            sh("echo 'Part 1: 42'; echo noise; echo 'Part 2: x'"),
+            Duration::from_secs(5),
+        );
+        assert!(matches!(&parts[0], Outcome::Solved { answer, .. } if answer == "42"));
+        assert!(matches!(&parts[1], Outcome::Solved { answer, .. } if answer == "x"));
+    }
+
+    #[test]
+    fn test_run_parts_reads_stats() {
+        let parts = run_parts(
+            sh("echo 'Part 1: 42'; echo 'Part 1 stats: 1.000ms, 2 allocations'; echo 'Part 2: x'"),
+            Duration::from_secs(5),
+        );
+        assert!(matches!(
+            &parts[0],
+            Outcome::Solved { stats: Some(stats), .. } if stats == "1.000ms, 2 allocations"
+        ));
+        assert!(matches!(&parts[1], Outcome::Solved { stats: None, .. }));
+        assert!(format_outcome(&parts[0]).starts_with("42 (1.000ms, 2 allocations)"));
+    }
+
+    #[test]
+    fn test_run_parts_times_out() {
+        let started = Instant::now();
+        let parts = run_parts(
+            sh("echo 'Part 1: 1'; exec sleep 10"),
+            Duration::from_millis(200),
+        );
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert!(matches!(parts[0], Outcome::Solved { .. }));
+        assert_eq!(parts[1], Outcome::TimedOut);
+
+        let parts = run_parts(sh("exec sleep 10"), Duration::from_millis(200));
+        assert_eq!(parts, vec![Outcome::TimedOut, Outcome::NotRun]);
+    }
+
+    #[test]
+    fn test_run_parts_failure() {
+        let parts = run_parts(sh("echo 'Part 1: 1'; exit 3"), Duration::from_secs(5));
+        assert!(matches!(&parts[1], Outcome::Failed(reason) if reason.contains('3')));
+
+        let parts = run_parts(
+            sh("echo 'Part 1 went over the allocation budget' >&2; exit 1"),
+            Duration::from_secs(5),
+        );
+        assert!(matches!(
+            &parts[0],
+            Outcome::Failed(reason) if reason.ends_with(": Part 1 went over the allocation budget")
+        ));
+    }
+
+    #[test]
+    fn test_summary() {
+        let solved = || Outcome::Solved {
+            answer: "1".to_string(),
+            elapsed: Duration::from_millis(5),
+            stats: None,
+        };
+        let runs = [
+            Run {
+                solver: solver("2022", "day01"),
+                parts: vec![solved(), solved()],
+            },
+            Run {
+                solver: solver("2023", "day05"),
+                parts: vec![solved(), Outcome::TimedOut],
+            },
+            Run {
+                solver: solver("2023", "day07"),
+                parts: vec![Outcome::Failed("build failed".to_string()), Outcome::NotRun],
+            },
+        ];
+        assert_eq!(
+            summary(&runs),
+            "2023/day07   part 1: failed, build failed\n\
+             2022   1 solvers, 2/2 parts solved, 0 timed out, 0 failed, 10.000ms solving\n\
+             2023   2 solvers, 1/4 parts solved, 1 timed out, 2 failed, 5.000ms solving\n\
+             total  3 solvers, 3/6 parts solved, 1 timed out, 2 failed, 15.000ms solving"
+        );
+        assert_eq!(
+            format_run(&runs[1]),
+            "2023/day05   part 1: 1 (5.000ms)  part 2: timed out"
+        );
+    }
+}
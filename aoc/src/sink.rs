@@ -0,0 +1,421 @@
+//! Destinations for the snapshots a simulation produces.
+//!
+//! Solvers push [`Frame`]s to a [`FrameSink`] and leave it to the sink to decide what
+//! to do with them: [`FileSink`] saves them as a GIF or PNG, [`AnsiSink`] animates
+//! them in place in the terminal and [`TextSink`] prints them one after another,
+//! which is what `--animate` falls back to when stdout is not a terminal.
+
+use std::fs::File;
+use std::io::{self, IsTerminal, Read, Stdout, Write};
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::{Duration, Instant};
+
+use crate::image::{self, Frame, Rgb};
+
+pub trait FrameSink {
+    fn push(&mut self, frame: &Frame) -> io::Result<()>;
+
+    /// Called once after the last frame.
+    fn finish(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Keeps every frame and saves them with [`image::save`] when finished.
+pub struct FileSink {
+    path: PathBuf,
+    palette: Vec<Rgb>,
+    scale: usize,
+    delay: u16,
+    frames: Vec<Frame>,
+}
+
+impl FileSink {
+    /// Frames are scaled up `scale` times and shown for `delay` hundredths of a second.
+    pub fn new(path: PathBuf, palette: &[Rgb], scale: usize, delay: u16) -> Self {
+        FileSink {
+            path,
+            palette: palette.to_vec(),
+            scale,
+            delay,
+            frames: vec![],
+        }
+    }
+}
+
+impl FrameSink for FileSink {
+    fn push(&mut self, frame: &Frame) -> io::Result<()> {
+        self.frames.push(frame.scaled(self.scale));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        image::save(&self.path, &self.frames, &self.palette, self.delay)
+    }
+}
+
+/// Prints each frame as text, one `legend` character per palette index, with a blank
+/// line after it.
+pub struct TextSink<W: Write> {
+    out: W,
+    legend: Vec<char>,
+}
+
+impl<W: Write> TextSink<W> {
+    pub fn new(out: W, legend: &str) -> Self {
+        TextSink {
+            out,
+            legend: legend.chars().collect(),
+        }
+    }
+}
+
+impl<W: Write> FrameSink for TextSink<W> {
+    fn push(&mut self, frame: &Frame) -> io::Result<()> {
+        for row in frame.pixels.chunks(frame.width.max(1)) {
+            let line = row
+                .iter()
+                .map(|p| self.legend.get(*p as usize).copied().unwrap_or('?'))
+                .collect::<String>();
+            writeln!(self.out, "{}", line)?;
+        }
+        writeln!(self.out)
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Draws a frame with 24-bit colour escapes, two pixel rows per line of text: the
+/// upper half block takes the top pixel's colour and its background the bottom's.
+fn ansi_frame(frame: &Frame, palette: &[Rgb]) -> String {
+    let colour = |x: usize, y: usize| {
+        if y < frame.height {
+            palette[frame.get(x, y) as usize]
+        } else {
+            [0, 0, 0]
+        }
+    };
+    let mut out = String::new();
+    for y in (0..frame.height).step_by(2) {
+        let mut previous = None;
+        for x in 0..frame.width {
+            let (top, bottom) = (colour(x, y), colour(x, y + 1));
+            if previous != Some((top, bottom)) {
+                out += &format!(
+                    "\x1b[38;2;{};{};{};48;2;{};{};{}m",
+                    top[0], top[1], top[2], bottom[0], bottom[1], bottom[2]
+                );
+                previous = Some((top, bottom));
+            }
+            out.push('▀');
+        }
+        out += "\x1b[0m\x1b[K\n";
+    }
+    out
+}
+
+/// Puts the controlling terminal into unbuffered, no-echo mode and reads key presses
+/// on a background thread, stopping that thread and restoring the previous mode when
+/// dropped. Reads time out every tenth of a second so the thread notices the stop.
+struct Keyboard {
+    keys: Receiver<u8>,
+    stop: Arc<AtomicBool>,
+    reader: Option<JoinHandle<()>>,
+    saved_mode: String,
+}
+
+impl Keyboard {
+    fn open() -> Option<Keyboard> {
+        let stty = |args: &[&str]| {
+            Command::new("stty")
+                .args(args)
+                .stdin(File::open("/dev/tty").ok()?)
+                .stderr(Stdio::null())
+                .output()
+                .ok()
+                .filter(|o| o.status.success())
+        };
+        let saved_mode = String::from_utf8(stty(&["-g"])?.stdout).ok()?;
+        let saved_mode = saved_mode.trim().to_string();
+        stty(&["-icanon", "-echo", "min", "0", "time", "1"])?;
+        let Ok(mut tty) = File::open("/dev/tty") else {
+            let _ = stty(&[&saved_mode]);
+            return None;
+        };
+        let (sender, keys) = mpsc::channel();
+        let stop = Arc::new(AtomicBool::new(false));
+        let stopped = stop.clone();
+        let reader = thread::spawn(move || {
+            let mut byte = [0u8];
+            while !stopped.load(Ordering::Relaxed) {
+                match tty.read(&mut byte) {
+                    Ok(0) => {}
+                    Ok(_) if sender.send(byte[0]).is_ok() => {}
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                    _ => break,
+                }
+            }
+        });
+        Some(Keyboard {
+            keys,
+            stop,
+            reader: Some(reader),
+            saved_mode,
+        })
+    }
+}
+
+impl Drop for Keyboard {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+        if let Some(reader) = self.reader.take() {
+            let _ = reader.join();
+        }
+        if let Ok(tty) = File::open("/dev/tty") {
+            let _ = Command::new("stty")
+                .arg(&self.saved_mode)
+                .stdin(tty)
+                .status();
+        }
+    }
+}
+
+/// How far `+` and `-` can take the frame time, so the rate stays finite and nonzero.
+const FRAME_TIMES: (Duration, Duration) = (Duration::from_millis(1), Duration::from_secs(100));
+
+/// Redraws each frame in place at a fixed rate. Space pauses and resumes, `n` shows
+/// the next frame while paused, `+` and `-` double or halve the speed and `q` skips
+/// the rest of the animation.
+pub struct AnsiSink {
+    out: Stdout,
+    palette: Vec<Rgb>,
+    frame_time: Duration,
+    last_drawn: Option<Instant>,
+    keyboard: Option<Keyboard>,
+    paused: bool,
+    quit: bool,
+}
+
+impl AnsiSink {
+    pub fn new(palette: &[Rgb], fps: f64) -> Self {
+        AnsiSink {
+            out: io::stdout(),
+            palette: palette.to_vec(),
+            frame_time: Duration::from_secs_f64(1.0 / fps.clamp(0.01, 1000.0)),
+            last_drawn: None,
+            keyboard: Keyboard::open(),
+            paused: false,
+            quit: false,
+        }
+    }
+
+    /// Handles pending key presses and returns whether one asked for a single step.
+    fn read_keys(&mut self) -> bool {
+        let mut step = false;
+        let Some(keyboard) = &self.keyboard else {
+            return false;
+        };
+        let keys = keyboard.keys.try_iter().collect::<Vec<_>>();
+        for key in keys {
+            step |= self.press(key);
+        }
+        step
+    }
+
+    /// Applies one key press and returns whether it asked for a single step.
+    fn press(&mut self, key: u8) -> bool {
+        let (fastest, slowest) = FRAME_TIMES;
+        match key {
+            b' ' => self.paused = !self.paused,
+            b'n' => return true,
+            b'+' => self.frame_time = (self.frame_time / 2).max(fastest),
+            b'-' => self.frame_time = (self.frame_time * 2).min(slowest),
+            b'q' => self.quit = true,
+            _ => {}
+        }
+        false
+    }
+
+    fn status(&self) -> String {
+        let state = if self.paused { "paused" } else { "playing" };
+        format!(
+            "{} at {:.1} fps: space pause, n step, +/- speed, q skip\x1b[K",
+            state,
+            1.0 / self.frame_time.as_secs_f64()
+        )
+    }
+}
+
+impl FrameSink for AnsiSink {
+    fn push(&mut self, frame: &Frame) -> io::Result<()> {
+        if self.quit {
+            return Ok(());
+        }
+        if self.last_drawn.is_none() {
+            write!(self.out, "\x1b[2J\x1b[?25l")?;
+        }
+        loop {
+            let step = self.read_keys();
+            if self.quit {
+                return Ok(());
+            }
+            let due = self
+                .last_drawn
+                .is_none_or(|t| t.elapsed() >= self.frame_time);
+            if step || (!self.paused && due) {
+                break;
+            }
+            thread::sleep(Duration::from_millis(5));
+        }
+        write!(
+            self.out,
+            "\x1b[H{}{}",
+            ansi_frame(frame, &self.palette),
+            self.status()
+        )?;
+        self.out.flush()?;
+        self.last_drawn = Some(Instant::now());
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.keyboard = None;
+        writeln!(self.out, "\x1b[0m\x1b[?25h")?;
+        self.out.flush()
+    }
+}
+
+impl Drop for AnsiSink {
+    fn drop(&mut self) {
+        let _ = write!(self.out, "\x1b[0m\x1b[?25h");
+    }
+}
+
+/// What the command line asked for: `--render <path>` saves to a file, `--animate`
+/// plays in the terminal, and `--fps <n>` sets the rate for either.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Output {
+    File(PathBuf),
+    Terminal,
+}
+
+pub fn parse_args(args: impl IntoIterator<Item = String>) -> (Option<Output>, Option<f64>) {
+    let mut output = None;
+    let mut fps = None;
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, inline) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        match flag.as_str() {
+            "--render" => {
+                output = inline
+                    .or_else(|| args.next())
+                    .map(|p| Output::File(p.into()))
+            }
+            "--animate" => output = Some(Output::Terminal),
+            "--fps" => fps = inline.or_else(|| args.next()).and_then(|f| f.parse().ok()),
+            _ => {}
+        }
+    }
+    (output, fps)
+}
+
+/// The sink the command line asked for, if any. `legend` gives a character per
+/// palette index for plain text output, `scale` enlarges file output and `fps` is the
+/// default rate.
+pub fn from_args(
+    palette: &[Rgb],
+    legend: &str,
+    scale: usize,
+    fps: f64,
+) -> Option<Box<dyn FrameSink>> {
+    let (output, fps_arg) = parse_args(std::env::args().skip(1));
+    let fps = fps_arg.unwrap_or(fps);
+    Some(match output? {
+        Output::File(path) => {
+            let delay = (100.0 / fps).round().clamp(1.0, u16::MAX as f64) as u16;
+            Box::new(FileSink::new(path, palette, scale, delay))
+        }
+        Output::Terminal if io::stdout().is_terminal() => Box::new(AnsiSink::new(palette, fps)),
+        Output::Terminal => Box::new(TextSink::new(io::stdout(), legend)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_text_sink() {
+        let mut out = vec![];
+        let mut sink = TextSink::new(&mut out, ".#");
+        sink.push(&Frame::from_fn(3, 2, |x, y| (x == y) as u8))
+            .unwrap();
+        sink.push(&Frame::new(1, 1)).unwrap();
+        sink.finish().unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "#..\n.#.\n\n.\n\n");
+    }
+
+    #[test]
+    fn test_ansi_frame() {
+        let frame = Frame::from_fn(2, 3, |x, y| (x + y) as u8 % 2);
+        let drawn = ansi_frame(&frame, &[[0, 0, 0], [255, 255, 255]]);
+        let lines = drawn.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(
+            lines[0],
+            "\x1b[38;2;0;0;0;48;2;255;255;255m▀\x1b[38;2;255;255;255;48;2;0;0;0m▀\x1b[0m\x1b[K"
+        );
+        // The odd last row is drawn over black.
+        assert!(lines[1].starts_with("\x1b[38;2;0;0;0;48;2;0;0;0m▀"));
+    }
+
+    #[test]
+    fn test_speed_keys_stay_in_range() {
+        let mut sink = AnsiSink::new(&[[0, 0, 0]], 25.0);
+        (0..64).for_each(|_| assert!(!sink.press(b'+')));
+        assert_eq!(sink.frame_time, FRAME_TIMES.0);
+        assert!(sink.status().contains("at 1000.0 fps"));
+        (0..64).for_each(|_| assert!(!sink.press(b'-')));
+        assert_eq!(sink.frame_time, FRAME_TIMES.1);
+        assert!(sink.press(b'n'));
+    }
+
+    #[test]
+    fn test_parse_args() {
+        let args = |s: &str| s.split_whitespace().map(String::from).collect::<Vec<_>>();
+        assert_eq!(
+            parse_args(args("--render out.gif --fps 12.5")),
+            (Some(Output::File("out.gif".into())), Some(12.5))
+        );
+        assert_eq!(
+            parse_args(args("--fps=3 --animate")),
+            (Some(Output::Terminal), Some(3.0))
+        );
+        assert_eq!(
+            parse_args(args("--render=a.png")).0,
+            Some(Output::File("a.png".into()))
+        );
+        assert_eq!(parse_args(args("input.txt")), (None, None));
+    }
+
+    #[test]
+    fn test_file_sink() {
+        let path = std::env::temp_dir().join(format!("aoc-sink-{}.png", std::process::id()));
+        let mut sink = FileSink::new(path.clone(), &[[0, 0, 0], [9, 9, 9]], 3, 10);
+        sink.push(&Frame::new(2, 1)).unwrap();
+        sink.finish().unwrap();
+        let png = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&png[16..24], &[0, 0, 0, 6, 0, 0, 0, 3]);
+    }
+}
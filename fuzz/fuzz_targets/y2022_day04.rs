@@ -0,0 +1,8 @@
+#![no_main]
+#![feature(test)]
+
+#[path = "../../2022/day04/src/main.rs"]
+#[allow(dead_code, unused_attributes)]
+mod day;
+
+libfuzzer_sys::fuzz_target!(|data: &[u8]| day::fuzz_parse(data));